@@ -0,0 +1,255 @@
+use crate::calendar;
+use crate::config::Zone;
+use crate::holidays::WorkCalendar;
+use crate::output::{self, Record, Value};
+use crate::shared::{RecurFrom, Task, TaskStatus};
+use crate::time_parse::format_duration;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Bounds the expansion of very frequent series, e.g. every minute over a week
+const MAX_PROJECTED_PER_SERIES: usize = 500;
+
+/// Days an agenda covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AgendaRange {
+    Today,
+    Tomorrow,
+    /// Today and the next 6 days
+    Week,
+}
+
+impl AgendaRange {
+    pub fn days(self, now: DateTime<Utc>, zone: Zone) -> Vec<NaiveDate> {
+        let today = zone.to_local(now).date();
+        match self {
+            AgendaRange::Today => vec![today],
+            AgendaRange::Tomorrow => vec![today + Duration::days(1)],
+            AgendaRange::Week => (0..7).map(|offset| today + Duration::days(offset)).collect(),
+        }
+    }
+}
+
+/// A task as it falls in a time window
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    pub task: Task,
+    /// Not stored yet: the series will create it when the previous occurrence ends
+    pub projected: bool,
+}
+
+/// Stored tasks overlapping `from`..`to`, plus the occurrences recurring series will create
+/// in that window, ordered by start
+pub fn occurrences(tasks: &[Task], calendar: &WorkCalendar, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Occurrence> {
+    let in_window = |task: &Task| task.start_time < to && task.end_time > from;
+    let mut found: Vec<Occurrence> = tasks
+        .iter()
+        .filter(|task| in_window(task))
+        .map(|task| Occurrence { task: task.clone(), projected: false })
+        .collect();
+
+    // The latest recurring occurrence of each series is the one that drives the chain
+    let mut heads: HashMap<usize, &Task> = HashMap::new();
+    for task in tasks.iter().filter(|task| task.is_recurring && task.recur_from == RecurFrom::Schedule) {
+        let is_latest = heads.get(&task.series()).is_none_or(|head| head.start_time < task.start_time);
+        if is_latest {
+            heads.insert(task.series(), task);
+        }
+    }
+    for head in heads.into_values() {
+        let mut current = head.clone();
+        for _ in 0..MAX_PROJECTED_PER_SERIES {
+            let Some(next) = current.next_instance(calendar) else { break };
+            if next.start_time >= to {
+                break;
+            }
+            if in_window(&next) {
+                found.push(Occurrence { task: next.clone(), projected: true });
+            }
+            current = next;
+        }
+    }
+
+    found.sort_by_key(|occurrence| (occurrence.task.start_time, occurrence.projected, occurrence.task.id));
+    found
+}
+
+/// One record per occurrence for the machine-readable formats
+pub fn record(occurrence: &Occurrence) -> Record {
+    let task = &occurrence.task;
+    Record::new()
+        .field("id", if occurrence.projected { Value::Null } else { Value::Int(task.id as i64) })
+        .field("start", Value::Time(task.start_time))
+        .field("end", Value::Time(task.end_time))
+        .field("duration", Value::Text(format_duration(task.end_time - task.start_time)))
+        .field("title", Value::Text(task.title.clone()))
+        .field("status", Value::Text(task.status.to_string()))
+        .field("recurring", Value::Bool(task.series_id.is_some()))
+        .field("projected", Value::Bool(occurrence.projected))
+}
+
+/// The agenda as text: a heading per day, then its tasks with local times and durations.
+/// `↻` marks recurring tasks; a "now" line shows where the current time falls today.
+pub fn render(occurrences: &[Occurrence], days: &[NaiveDate], zone: Zone, now: DateTime<Utc>, colour: bool) -> String {
+    let today = zone.to_local(now).date();
+    let mut out = String::new();
+    for (index, day) in days.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let (day_start, day_end) = calendar::bounds(*day, zone);
+        let mut heading = day.format("%A %Y-%m-%d").to_string();
+        if *day == today {
+            heading.push_str(" (today)");
+        }
+        let _ = writeln!(out, "{}", output::paint(&heading, output::BOLD, colour));
+
+        let on_day: Vec<&Occurrence> = occurrences
+            .iter()
+            .filter(|occurrence| occurrence.task.start_time < day_end && occurrence.task.end_time > day_start)
+            .collect();
+        if on_day.is_empty() {
+            let _ = writeln!(out, "  {}", output::paint("Nothing scheduled.", output::DIM, colour));
+        }
+
+        let mut now_shown = *day != today;
+        for occurrence in on_day {
+            let task = &occurrence.task;
+            if !now_shown && task.start_time > now {
+                let _ = writeln!(out, "{}", now_line(zone, now, colour));
+                now_shown = true;
+            }
+            let clock = |time: DateTime<Utc>| {
+                if time >= day_start && time < day_end {
+                    zone.to_local(time).format("%H:%M").to_string()
+                } else {
+                    "  …  ".to_string()
+                }
+            };
+            let mut line = format!(
+                "  {}-{}  {:>6}  {} {}",
+                clock(task.start_time),
+                if task.end_time == day_end { "24:00".to_string() } else { clock(task.end_time) },
+                format_duration(task.end_time - task.start_time),
+                if task.series_id.is_some() { "↻" } else { " " },
+                task.title
+            );
+            if occurrence.projected {
+                line.push_str("  (upcoming)");
+            } else {
+                let _ = write!(line, "  [{}]", task.id);
+            }
+            match task.status {
+                TaskStatus::Pending if task.start_time <= now && now < task.end_time => {
+                    line.push_str("  ◀ now");
+                    now_shown = true;
+                }
+                TaskStatus::Pending => {}
+                status => {
+                    let _ = write!(line, "  ({})", status);
+                }
+            }
+            let style = match task.status {
+                TaskStatus::Done => output::GREEN,
+                TaskStatus::Missed => output::RED,
+                TaskStatus::Pending if occurrence.projected => output::DIM,
+                TaskStatus::Pending => "",
+            };
+            let _ = writeln!(out, "{}", output::paint(&line, style, colour));
+        }
+        if !now_shown {
+            let _ = writeln!(out, "{}", now_line(zone, now, colour));
+        }
+    }
+    out
+}
+
+fn now_line(zone: Zone, now: DateTime<Utc>, colour: bool) -> String {
+    let line = format!("  ── now {} ──", zone.to_local(now).format("%H:%M"));
+    output::paint(&line, output::YELLOW, colour)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn task(id: usize, title: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        let mut task = Task::new(title.into(), String::new(), start, end, false, None, None);
+        task.id = id;
+        task
+    }
+
+    fn daily(id: usize, title: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        let mut task = task(id, title, start, end);
+        task.is_recurring = true;
+        task.series_id = Some(id);
+        task.recurrence = Some("FREQ=DAILY".parse().unwrap());
+        task
+    }
+
+    fn calendar(hours: i32) -> WorkCalendar {
+        WorkCalendar { zone: Zone::Fixed(FixedOffset::east_opt(hours * 3600).unwrap()), ..WorkCalendar::default() }
+    }
+
+    fn summary(found: &[Occurrence]) -> Vec<(usize, DateTime<Utc>, bool)> {
+        found.iter().map(|occurrence| (occurrence.task.id, occurrence.task.start_time, occurrence.projected)).collect()
+    }
+
+    #[test]
+    fn series_are_projected_from_their_latest_occurrence() {
+        let mut past = task(1, "Standup", at(1, 9), at(1, 10));
+        past.series_id = Some(1);
+        let mut head = daily(2, "Standup", at(2, 9), at(2, 10));
+        head.series_id = Some(1);
+        let mut chore = daily(3, "Water plants", at(2, 18), at(2, 19));
+        chore.recur_from = RecurFrom::Completion;
+        let tasks = [past, head, chore, task(4, "Lunch", at(3, 9), at(3, 11))];
+
+        let found = occurrences(&tasks, &calendar(0), at(1, 0), at(4, 9));
+        assert_eq!(
+            summary(&found),
+            [(1, at(1, 9), false), (2, at(2, 9), false), (3, at(2, 18), false), (4, at(3, 9), false), (0, at(3, 9), true)]
+        );
+        assert!(found.iter().filter(|occurrence| occurrence.projected).all(|occurrence| occurrence.task.series() == 1));
+
+        // windows after the stored occurrences only hold projections
+        assert_eq!(summary(&occurrences(&tasks, &calendar(0), at(5, 0), at(7, 0))), [(0, at(5, 9), true), (0, at(6, 9), true)]);
+        let ended = Task { recurrence: Some("FREQ=DAILY;COUNT=2".parse().unwrap()), ..tasks[1].clone() };
+        assert!(occurrences(&[ended], &calendar(0), at(5, 0), at(7, 0)).is_empty());
+    }
+
+    #[test]
+    fn days_are_grouped_in_local_time() {
+        // UTC+10: the standup is at 08:00 local, the night shift runs past local midnight
+        let calendar = calendar(10);
+        let mut review = task(3, "Review", at(2, 0), at(2, 1));
+        review.status = TaskStatus::Done;
+        let tasks = [daily(1, "Standup", at(1, 22), at(1, 22) + Duration::minutes(15)), task(2, "Night shift", at(2, 12), at(2, 16)), review];
+        let now = at(1, 23);
+        let days = [NaiveDate::from_ymd_opt(2030, 1, 2).unwrap(), NaiveDate::from_ymd_opt(2030, 1, 3).unwrap()];
+        let (from, _) = calendar::bounds(days[0], calendar.zone);
+        let (_, to) = calendar::bounds(days[1], calendar.zone);
+
+        let found = occurrences(&tasks, &calendar, from, to);
+        assert_eq!(
+            render(&found, &days, calendar.zone, now, false),
+            "Wednesday 2030-01-02 (today)\n  \
+             08:00-08:15     15m  ↻ Standup  [1]\n  \
+             ── now 09:00 ──\n  \
+             10:00-11:00      1h    Review  [3]  (done)\n  \
+             22:00-  …        4h    Night shift  [2]\n\
+             \n\
+             Thursday 2030-01-03\n    \
+             …  -02:00      4h    Night shift  [2]\n  \
+             08:00-08:15     15m  ↻ Standup  (upcoming)\n"
+        );
+        assert_eq!(AgendaRange::Tomorrow.days(now, calendar.zone), [days[1]]);
+    }
+}
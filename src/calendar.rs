@@ -0,0 +1,269 @@
+use crate::agenda::Occurrence;
+use crate::config::Zone;
+use crate::error::TaskError;
+use crate::output::{self, Record, Value};
+use crate::shared::TaskStatus;
+use crate::time_parse::format_duration;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use std::fmt::Write;
+
+// Characters of text per day cell
+const CELL_WIDTH: usize = 12;
+// Most task lines per cell before the rest are summarised as "+N more"
+const MONTH_LINES: usize = 3;
+const WEEK_LINES: usize = 10;
+const INVERSE: &str = "\x1b[7m";
+
+/// Parses a month such as "2025-03" into its first day
+pub fn parse_month(input: &str) -> Result<NaiveDate, TaskError> {
+    NaiveDate::parse_from_str(&format!("{}-01", input.trim()), "%Y-%m-%d")
+        .map_err(|_| TaskError::InvalidTime(format!("invalid month '{}', expected e.g. 2025-03", input)))
+}
+
+/// The days a grid shows, whole weeks from Monday to Sunday
+#[derive(Debug, Clone, Copy)]
+pub enum Span {
+    /// The weeks covering the month starting on this day
+    Month(NaiveDate),
+    /// The week containing this day
+    Week(NaiveDate),
+}
+
+impl Span {
+    pub fn days(self) -> Vec<NaiveDate> {
+        let (first, last) = match self {
+            Span::Month(first) => {
+                let next = first.checked_add_months(chrono::Months::new(1)).unwrap_or(first);
+                (first, next - Duration::days(1))
+            }
+            Span::Week(day) => (day, day),
+        };
+        let start = first - Duration::days(first.weekday().num_days_from_monday().into());
+        let end = last + Duration::days((6 - last.weekday().num_days_from_monday()).into());
+        start.iter_days().take_while(|day| *day <= end).collect()
+    }
+
+    fn in_focus(self, day: NaiveDate) -> bool {
+        match self {
+            Span::Month(first) => day.year() == first.year() && day.month() == first.month(),
+            Span::Week(_) => true,
+        }
+    }
+}
+
+/// What is scheduled on one day of the grid
+pub struct Day<'a> {
+    pub date: NaiveDate,
+    pub occurrences: Vec<&'a Occurrence>,
+    /// Scheduled time within the day, counting overlapping tasks in full
+    pub busy: Duration,
+    /// More is booked than fits in the working day
+    pub overloaded: bool,
+}
+
+/// Splits occurrences over the days they occupy. Missed tasks don't count towards the load.
+pub fn days<'a>(occurrences: &'a [Occurrence], dates: &[NaiveDate], zone: Zone, capacity: Duration) -> Vec<Day<'a>> {
+    dates
+        .iter()
+        .map(|date| {
+            let (start, end) = bounds(*date, zone);
+            let on_day: Vec<&Occurrence> = occurrences
+                .iter()
+                .filter(|occurrence| occurrence.task.start_time < end && occurrence.task.end_time > start)
+                .collect();
+            let busy = on_day
+                .iter()
+                .filter(|occurrence| occurrence.task.status != TaskStatus::Missed)
+                .map(|occurrence| occurrence.task.end_time.min(end) - occurrence.task.start_time.max(start))
+                .fold(Duration::zero(), |total, part| total + part);
+            Day { date: *date, occurrences: on_day, busy, overloaded: busy > capacity }
+        })
+        .collect()
+}
+
+/// The UTC instants a local day runs between
+pub fn bounds(date: NaiveDate, zone: Zone) -> (DateTime<Utc>, DateTime<Utc>) {
+    (
+        zone.to_utc(date.and_time(NaiveTime::MIN)),
+        zone.to_utc((date + Duration::days(1)).and_time(NaiveTime::MIN)),
+    )
+}
+
+/// One record per day for the machine-readable formats
+pub fn record(day: &Day) -> Record {
+    Record::new()
+        .field("date", Value::Text(day.date.to_string()))
+        .field("tasks", Value::Int(day.occurrences.len() as i64))
+        .field("busy", Value::Text(format_duration(day.busy)))
+        .field("overloaded", Value::Bool(day.overloaded))
+        .field("titles", Value::List(day.occurrences.iter().map(|occurrence| occurrence.task.title.clone()).collect()))
+}
+
+/// The grid as text. Today is inverted (bracketed without colour) and overloaded days
+/// are red (marked with "!").
+pub fn render(days: &[Day], span: Span, zone: Zone, today: NaiveDate, colour: bool) -> String {
+    let max_lines = match span {
+        Span::Month(_) => MONTH_LINES,
+        Span::Week(_) => WEEK_LINES,
+    };
+    let border = format!("+{}\n", format!("{}+", "-".repeat(CELL_WIDTH + 2)).repeat(7));
+    let mut out = String::new();
+
+    let title = match span {
+        Span::Month(first) => first.format("%B %Y").to_string(),
+        Span::Week(day) => format!("Week {} of {}", day.iso_week().week(), day.iso_week().year()),
+    };
+    let _ = writeln!(out, "{}", output::paint(&title, output::BOLD, colour));
+    out.push_str(&border);
+    out.push('|');
+    for name in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+        let _ = write!(out, " {} |", output::paint(&fit(name), output::BOLD, colour));
+    }
+    out.push('\n');
+    out.push_str(&border);
+
+    for week in days.chunks(7) {
+        // Rows grow with the busiest day of the week, up to the limit
+        let lines = week.iter().map(|day| day.occurrences.len()).max().unwrap_or(0).clamp(1, max_lines);
+        let mut rows: Vec<String> = vec!["|".to_string(); lines + 1];
+        for day in week {
+            let label = match (day.date == today, day.overloaded) {
+                (true, true) => format!("[{}] !", day.date.day()),
+                (true, false) => format!("[{}]", day.date.day()),
+                (false, true) => format!("{} !", day.date.day()),
+                (false, false) => day.date.day().to_string(),
+            };
+            let style = if !span.in_focus(day.date) {
+                output::DIM.to_string()
+            } else {
+                let mut style = String::new();
+                if day.date == today {
+                    style.push_str(INVERSE);
+                }
+                if day.overloaded {
+                    style.push_str(output::RED);
+                }
+                style
+            };
+            let _ = write!(rows[0], " {} |", output::paint(&fit(&label), &style, colour));
+
+            let (start, _) = bounds(day.date, zone);
+            let mut cells: Vec<(String, &str)> = day
+                .occurrences
+                .iter()
+                .map(|occurrence| {
+                    let task = &occurrence.task;
+                    let time = if task.start_time >= start {
+                        zone.to_local(task.start_time).format("%H:%M").to_string()
+                    } else {
+                        "…".to_string()
+                    };
+                    let style = match task.status {
+                        TaskStatus::Done => output::GREEN,
+                        TaskStatus::Missed => output::RED,
+                        TaskStatus::Pending if occurrence.projected => output::DIM,
+                        TaskStatus::Pending => "",
+                    };
+                    (format!("{} {}", time, task.title), style)
+                })
+                .collect();
+            if cells.len() > lines {
+                let hidden = cells.len() - (lines - 1);
+                cells.truncate(lines - 1);
+                cells.push((format!("+{} more", hidden), output::DIM));
+            }
+            cells.resize(lines, (String::new(), ""));
+            for (row, (text, style)) in rows[1..].iter_mut().zip(cells) {
+                let _ = write!(row, " {} |", output::paint(&fit(&text), style, colour));
+            }
+        }
+        for row in rows {
+            let _ = writeln!(out, "{}", row);
+        }
+        out.push_str(&border);
+    }
+    out
+}
+
+// Pads or cuts text to exactly the cell width
+fn fit(text: &str) -> String {
+    let count = text.chars().count();
+    if count > CELL_WIDTH {
+        text.chars().take(CELL_WIDTH - 1).chain(['…']).collect()
+    } else {
+        format!("{}{}", text, " ".repeat(CELL_WIDTH - count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::Task;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn utc() -> Zone {
+        Zone::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2030, month, day).unwrap()
+    }
+
+    fn at(month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, month, day, hour, 0, 0).unwrap()
+    }
+
+    fn occurrence(title: &str, start: DateTime<Utc>, end: DateTime<Utc>, status: TaskStatus) -> Occurrence {
+        let mut task = Task::new(title.into(), String::new(), start, end, false, None, None);
+        task.status = status;
+        Occurrence { task, projected: false }
+    }
+
+    #[test]
+    fn grids_cover_whole_weeks() {
+        // September 2030 starts on a Sunday and ends on a Monday
+        let days = Span::Month(date(9, 1)).days();
+        assert_eq!((days.len(), days[0], days[41]), (42, date(8, 26), date(10, 6)));
+        assert_eq!(Span::Week(date(9, 1)).days(), date(8, 26).iter_days().take(7).collect::<Vec<_>>());
+        assert_eq!(parse_month(" 2030-09 ").unwrap(), date(9, 1));
+        assert!(parse_month("2030-13").is_err());
+    }
+
+    #[test]
+    fn busy_time_is_split_at_midnight_and_skips_missed_tasks() {
+        let occurrences = [
+            occurrence("Night shift", at(9, 2, 20), at(9, 3, 4), TaskStatus::Pending),
+            occurrence("Planning", at(9, 3, 9), at(9, 3, 14), TaskStatus::Done),
+            occurrence("Standup", at(9, 3, 15), at(9, 3, 16), TaskStatus::Missed),
+        ];
+        let found = days(&occurrences, &[date(9, 2), date(9, 3), date(9, 4)], utc(), Duration::hours(8));
+        let summary: Vec<(usize, Duration, bool)> = found.iter().map(|day| (day.occurrences.len(), day.busy, day.overloaded)).collect();
+        assert_eq!(
+            summary,
+            [(1, Duration::hours(4), false), (3, Duration::hours(9), true), (0, Duration::zero(), false)]
+        );
+    }
+
+    #[test]
+    fn busy_days_are_cut_short_with_a_count() {
+        let mut occurrences: Vec<Occurrence> =
+            (9..14).map(|hour| occurrence(&format!("Call {}", hour), at(9, 4, hour), at(9, 4, hour + 1), TaskStatus::Pending)).collect();
+        occurrences.push(occurrence("Late night", at(9, 4, 23), at(9, 5, 1), TaskStatus::Pending));
+        let span = Span::Month(date(9, 1));
+        let grid = days(&occurrences, &span.days(), utc(), Duration::hours(8));
+        let text = render(&grid, span, utc(), date(9, 4), false);
+
+        assert!(text.starts_with("September 2030\n+--------------+"));
+        let lines: Vec<&str> = text.lines().collect();
+        let week = lines.iter().position(|line| line.contains("[4]")).unwrap();
+        // Wednesday column of the week with the 4th
+        let cell = |line: &str| line.split('|').nth(3).unwrap().to_string();
+        assert_eq!(cell(lines[week]), " [4]          ");
+        assert_eq!(cell(lines[week + 1]), " 09:00 Call 9 ");
+        assert_eq!(cell(lines[week + 2]), " 10:00 Call … ");
+        assert_eq!(cell(lines[week + 3]), " +4 more      ");
+        assert_eq!(lines[week + 1].split('|').nth(4).unwrap(), " … Late night ");
+        assert_eq!(fit("Standup"), "Standup     ");
+    }
+}
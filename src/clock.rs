@@ -0,0 +1,47 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Source of the current time, so commands and the daemon can run as if it were another moment
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Real time, moved so that it starts at a chosen instant (`--now`)
+pub struct ShiftedClock {
+    offset: Duration,
+}
+
+impl ShiftedClock {
+    pub fn starting_at(now: DateTime<Utc>) -> Self {
+        ShiftedClock { offset: now - Utc::now() }
+    }
+}
+
+impl Clock for ShiftedClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn shifted_clock_starts_at_the_chosen_instant_and_keeps_running() {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        let clock = ShiftedClock::starting_at(start);
+        let first = clock.now();
+        assert!(first >= start && first - start < Duration::seconds(1), "{} is not close to {}", first, start);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(clock.now() > first);
+    }
+}
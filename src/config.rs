@@ -0,0 +1,145 @@
+use crate::conflicts::ConflictPolicy;
+use crate::error::TaskError;
+use crate::notify::NotifierConfig;
+use crate::overdue::Escalation;
+use crate::quiet_hours::{QuietHours, QuietPolicy};
+use crate::reminder::Reminder;
+use crate::shared::Task;
+use crate::slots::WorkingHours;
+use crate::views::View;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Offset, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// User settings, read from a JSON file next to the task store.
+/// Every field is optional in the file; missing ones fall back to the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// UTC offset such as "+02:00" used for calendar days; the system timezone when unset
+    pub timezone: Option<String>,
+    /// Days of the week that count as working days
+    pub working_days: Vec<Weekday>,
+    /// Public holidays, as a .ics calendar or a CSV file with dates in the first column
+    pub holiday_file: Option<String>,
+    /// Reminders for tasks that don't set their own, e.g. ["start-5m", "end-2m"]
+    pub default_reminders: Vec<Reminder>,
+    /// Where reminders are delivered; stdout only by default
+    pub notifiers: Vec<NotifierConfig>,
+    /// "allow", "warn" or "refuse" tasks that overlap existing ones
+    pub conflict_policy: ConflictPolicy,
+    /// Local times between which free slots are searched, e.g. { "start": "09:00", "end": "17:00" }
+    pub working_hours: WorkingHours,
+    /// Periods without reminders, e.g. [{ "days": ["Mon"], "start": "22:00", "end": "07:00" }]
+    pub quiet_hours: Vec<QuietHours>,
+    /// "defer" reminders within quiet hours to their end, or "batch" them into one notification
+    pub quiet_policy: QuietPolicy,
+    /// Follow-up alerts for overdue tasks, e.g. { "after": ["1h", "1d"], "bump_priority": true }
+    pub escalation: Escalation,
+    /// Saved listings by name, run with `view <name>`
+    pub views: BTreeMap<String, View>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            timezone: None,
+            working_days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            holiday_file: None,
+            default_reminders: Reminder::defaults(),
+            notifiers: vec![NotifierConfig::Stdout],
+            conflict_policy: ConflictPolicy::Warn,
+            working_hours: WorkingHours::default(),
+            quiet_hours: Vec::new(),
+            quiet_policy: QuietPolicy::Defer,
+            escalation: Escalation::default(),
+            views: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, TaskError> {
+        if !Path::new(path).exists() {
+            return Ok(Config::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        let config: Config = serde_json::from_str(&contents)?;
+        config.zone()?;
+        config.working_hours.validate()?;
+        Ok(config)
+    }
+
+    /// Writes the settings back. Only settings already in the file or changed from their
+    /// defaults are written, so the file stays as short as the user left it.
+    pub fn save(&self, path: &str) -> Result<(), TaskError> {
+        let existing: serde_json::Map<String, serde_json::Value> = if Path::new(path).exists() {
+            serde_json::from_str(&fs::read_to_string(path)?)?
+        } else {
+            serde_json::Map::new()
+        };
+        let defaults = serde_json::to_value(Config::default())?;
+        let current = serde_json::to_value(self)?;
+        let kept: serde_json::Map<String, serde_json::Value> = current
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(key, value)| existing.contains_key(*key) || defaults.get(key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        fs::write(path, serde_json::to_string_pretty(&kept)? + "\n")?;
+        Ok(())
+    }
+
+    /// A task's own reminders replace the configured defaults
+    pub fn reminders_for<'a>(&'a self, task: &'a Task) -> &'a [Reminder] {
+        if task.reminders.is_empty() {
+            &self.default_reminders
+        } else {
+            &task.reminders
+        }
+    }
+
+    pub fn zone(&self) -> Result<Zone, TaskError> {
+        match self.timezone.as_deref() {
+            None | Some("local") => Ok(Zone::Local),
+            Some("UTC") | Some("utc") | Some("Z") => Ok(Zone::Fixed(Utc.fix())),
+            Some(offset) => offset
+                .parse::<FixedOffset>()
+                .map(Zone::Fixed)
+                .map_err(|_| TaskError::Config(format!("invalid timezone '{}', expected e.g. \"+02:00\"", offset))),
+        }
+    }
+}
+
+/// Timezone used to turn instants into calendar days and wall-clock times
+#[derive(Debug, Clone, Copy)]
+pub enum Zone {
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    pub fn to_local(self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => instant.with_timezone(&Local).naive_local(),
+            Zone::Fixed(offset) => instant.with_timezone(&offset).naive_local(),
+        }
+    }
+
+    /// The instant a local wall-clock time refers to. Times skipped by a DST change
+    /// resolve to the same wall-clock time shifted by the current offset.
+    pub fn to_utc(self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Zone::Local => Local
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(&(local - Local::now().offset().fix()))),
+            Zone::Fixed(offset) => Utc.from_utc_datetime(&(local - offset)),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::agenda::{self, Occurrence};
+use crate::holidays::WorkCalendar;
+use crate::shared::{Task, TaskStatus};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+// How far ahead recurring series are expanded when looking for conflicts without an end
+const PROJECTION_DAYS: i64 = 30;
+
+/// What adding or moving a task onto a slot that is already taken does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Say nothing
+    Allow,
+    /// Save the task but print a warning
+    #[default]
+    Warn,
+    /// Reject the change
+    Refuse,
+}
+
+/// Whether two tasks are scheduled at the same time. Tasks that merely touch
+/// (one ends when the other starts) don't overlap.
+pub fn overlaps(a: &Task, b: &Task) -> bool {
+    a.start_time < b.end_time && b.start_time < a.end_time
+}
+
+/// Every pair of overlapping pending tasks whose shared time falls at least partly
+/// within `from`..`to`, ordered by when the overlap begins. Occurrences recurring series
+/// will create count too, up to `to` or `PROJECTION_DAYS` past `from` without one.
+pub fn find_conflicts(
+    tasks: &[Task],
+    calendar: &WorkCalendar,
+    from: DateTime<Utc>,
+    to: Option<DateTime<Utc>>,
+) -> Vec<(Occurrence, Occurrence)> {
+    let horizon = to.unwrap_or(from + Duration::days(PROJECTION_DAYS));
+    let mut pending: Vec<Occurrence> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Pending)
+        .map(|task| Occurrence { task: task.clone(), projected: false })
+        .chain(agenda::occurrences(tasks, calendar, from, horizon).into_iter().filter(|occurrence| occurrence.projected))
+        .collect();
+    pending.sort_by_key(|occurrence| (occurrence.task.start_time, occurrence.projected, occurrence.task.id));
+
+    let mut pairs = Vec::new();
+    for (index, first) in pending.iter().enumerate() {
+        // sorted by start, so later tasks can't overlap once one starts after `first` ends
+        for second in pending[index + 1..].iter().take_while(|second| second.task.start_time < first.task.end_time) {
+            if !overlaps(&first.task, &second.task) {
+                continue;
+            }
+            // A long occurrence running into the next one of its own series isn't a clash
+            if (first.projected || second.projected) && first.task.series() == second.task.series() {
+                continue;
+            }
+            let overlap_start = first.task.start_time.max(second.task.start_time);
+            let overlap_end = first.task.end_time.min(second.task.end_time);
+            if overlap_end > from && to.is_none_or(|to| overlap_start < to) {
+                pairs.push((first.clone(), second.clone()));
+            }
+        }
+    }
+    pairs.sort_by_key(|(first, second)| {
+        (first.task.start_time.max(second.task.start_time), first.task.id, second.task.id)
+    });
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Zone;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn calendar() -> WorkCalendar {
+        WorkCalendar { zone: Zone::Fixed(FixedOffset::east_opt(0).unwrap()), ..WorkCalendar::default() }
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn task(id: usize, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        let mut task = Task::new(format!("Task {}", id), String::new(), start, end, false, None, None);
+        task.id = id;
+        task
+    }
+
+    fn daily(id: usize, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        let mut task = task(id, start, end);
+        task.is_recurring = true;
+        task.series_id = Some(id);
+        task.recurrence = Some("FREQ=DAILY".parse().unwrap());
+        task
+    }
+
+    #[test]
+    fn touching_tasks_do_not_overlap() {
+        assert!(!overlaps(&task(1, at(1, 9), at(1, 10)), &task(2, at(1, 10), at(1, 11))));
+        assert!(overlaps(&task(1, at(1, 9), at(1, 11)), &task(2, at(1, 10), at(1, 12))));
+    }
+
+    #[test]
+    fn pairs_of_pending_tasks_within_the_window() {
+        let mut done = task(3, at(1, 9), at(1, 12));
+        done.status = TaskStatus::Done;
+        let tasks = [task(1, at(1, 9), at(1, 11)), task(2, at(1, 10), at(1, 12)), done, task(4, at(5, 9), at(5, 11)), task(5, at(5, 10), at(5, 11))];
+        let pairs = find_conflicts(&tasks, &calendar(), at(1, 0), Some(at(2, 0)));
+        let ids: Vec<(usize, usize)> = pairs.iter().map(|(a, b)| (a.task.id, b.task.id)).collect();
+        assert_eq!(ids, [(1, 2)]);
+    }
+
+    #[test]
+    fn upcoming_occurrences_of_a_series_conflict() {
+        let tasks = [daily(1, at(1, 9), at(1, 17)), task(2, at(3, 10), at(3, 11))];
+        let pairs = find_conflicts(&tasks, &calendar(), at(2, 0), None);
+        assert_eq!(pairs.len(), 1);
+        let (first, second) = &pairs[0];
+        assert!(first.projected && first.task.series() == 1 && first.task.start_time == at(3, 9));
+        assert_eq!(second.task.id, 2);
+    }
+
+    #[test]
+    fn a_series_does_not_conflict_with_itself() {
+        // each occurrence runs into the next one
+        let tasks = [daily(1, at(1, 9), at(2, 10))];
+        assert!(find_conflicts(&tasks, &calendar(), at(1, 0), Some(at(5, 0))).is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::clock::Clock;
+use crate::config::Config;
+use crate::error::TaskError;
+use crate::notify::build_notifiers;
+use crate::reminder_state::ReminderState;
+use crate::scheduler::Scheduler;
+use crate::shared::{AppState, Task};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+
+// How often the daemon looks for changes made to the store by other commands
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Keeps reminders, overdue alerts and recurrences running for every task in the store until SIGTERM or Ctrl-C.
+/// Changes written to the store by other commands are picked up and rescheduled, and
+/// occurrences generated by the daemon are written back to it. Snoozes and acknowledgements
+/// are read from `reminder_file`, and used-up snoozes are removed from it.
+pub async fn run(
+    state: Arc<AppState>,
+    config: Config,
+    clock: Arc<dyn Clock>,
+    store_file: &str,
+    reminder_file: &str,
+) -> Result<(), TaskError> {
+    let notifiers = build_notifiers(&config.notifiers)?;
+    let mut scheduler = Scheduler::new(Arc::clone(&state), config, notifiers);
+    let mut last_modified = modified_time(store_file);
+    let mut last_saved = snapshot(&state);
+
+    scheduler.set_reminder_state(ReminderState::load(reminder_file)?);
+    let mut reminders_modified = modified_time(reminder_file);
+    let mut reminders_saved = scheduler.reminder_state().clone();
+
+    scheduler.sync(clock.now());
+    println!("Daemon started, watching {} task(s) in {}", scheduler.len(), store_file);
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        // One timer for the whole queue: sleep until the earliest event, or until the next poll
+        let next_fire = scheduler.next_fire_time();
+        let until_next = next_fire
+            .and_then(|at| (at - clock.now()).to_std().ok())
+            .unwrap_or(Duration::ZERO);
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = sleep(until_next), if next_fire.is_some() => {
+                scheduler.run_due(clock.now()).await?;
+            }
+            _ = poll.tick() => {
+                let modified = modified_time(store_file);
+                if modified != last_modified {
+                    // Another command changed the store, so it wins over our in-memory copy
+                    state.load_from_file(store_file).await?;
+                    for report in state.catch_up_recurrences(clock.now()).await? {
+                        println!("{}", report);
+                    }
+                    last_modified = modified;
+                    last_saved = snapshot(&state);
+                }
+                let modified = modified_time(reminder_file);
+                if modified != reminders_modified {
+                    // A snooze or ack command ran; snoozes used up here since the last save stay used up
+                    let saved = ReminderState::load(reminder_file)?;
+                    scheduler.set_reminder_state(scheduler.reminder_state().merge(&reminders_saved, saved.clone()));
+                    reminders_saved = saved;
+                    reminders_modified = modified;
+                }
+                scheduler.sync(clock.now());
+            }
+        }
+
+        let current = snapshot(&state);
+        if current != last_saved {
+            if modified_time(store_file) != last_modified {
+                // Another command wrote the store since we last read it, so our changes are
+                // replayed onto its version instead of overwriting it
+                merge_from_store(&state, store_file, &last_saved, &current).await?;
+            }
+            state.save_to_file(store_file).await?;
+            last_modified = modified_time(store_file);
+            last_saved = snapshot(&state);
+        }
+        if *scheduler.reminder_state() != reminders_saved {
+            if modified_time(reminder_file) != reminders_modified {
+                // A snooze or ack command ran since the last poll, so its changes are kept too
+                let saved = ReminderState::load(reminder_file)?;
+                scheduler.set_reminder_state(scheduler.reminder_state().merge(&reminders_saved, saved));
+                scheduler.sync(clock.now());
+            }
+            reminders_saved = scheduler.reminder_state().clone();
+            reminders_saved.save(reminder_file)?;
+            reminders_modified = modified_time(reminder_file);
+        }
+    }
+
+    println!("Shutting down daemon...");
+    Ok(())
+}
+
+// Tasks by ID, used to tell whether the daemon changed anything worth saving
+fn snapshot(state: &AppState) -> BTreeMap<usize, Task> {
+    state.tasks.iter().map(|entry| (*entry.key(), entry.value().clone())).collect()
+}
+
+// Reload the store and apply what the daemon changed since `base` on top of it. Tasks
+// edited or deleted by the other command keep its version; occurrences the daemon added
+// get a new ID if the other command took theirs.
+async fn merge_from_store(
+    state: &AppState,
+    store_file: &str,
+    base: &BTreeMap<usize, Task>,
+    current: &BTreeMap<usize, Task>,
+) -> Result<(), TaskError> {
+    state.load_from_file(store_file).await?;
+    for (id, original) in base {
+        if !current.contains_key(id) && state.tasks.get(id).is_some_and(|stored| stored.value() == original) {
+            state.tasks.remove(id);
+        }
+    }
+    for (id, task) in current {
+        match base.get(id) {
+            Some(original) if original == task => {}
+            Some(original) => {
+                if let Some(mut stored) = state.tasks.get_mut(id) {
+                    if stored.value() == original {
+                        *stored.value_mut() = task.clone();
+                    }
+                }
+            }
+            None if state.tasks.contains_key(id) => {
+                state.insert_task(task.clone()).await?;
+            }
+            None => {
+                state.tasks.insert(*id, task.clone());
+            }
+        }
+    }
+    let next_id = state.tasks.iter().map(|entry| *entry.key()).max().unwrap_or(0) + 1;
+    state.next_id.fetch_max(next_id, Ordering::SeqCst);
+    Ok(())
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            tokio::select! {
+                _ = sigterm.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflicts::ConflictPolicy;
+    use crate::holidays::WorkCalendar;
+    use crate::shared::Priority;
+    use chrono::{TimeZone, Utc};
+
+    fn state() -> AppState {
+        let folder = std::env::temp_dir().join("todo_task_tests");
+        AppState::new(folder.to_string_lossy().into_owned(), WorkCalendar::default(), ConflictPolicy::Allow).detached()
+    }
+
+    fn task(title: &str) -> Task {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        Task::new(title.into(), String::new(), start, start + chrono::Duration::hours(1), false, None, None)
+    }
+
+    #[tokio::test]
+    async fn changes_made_by_another_command_survive_a_daemon_save() {
+        let store = std::env::temp_dir().join(format!("todo_task_merge_{}.json", std::process::id()));
+        let store = store.to_str().unwrap();
+        let daemon = state();
+        daemon.insert_task(task("Report")).await.unwrap();
+        daemon.insert_task(task("Standup")).await.unwrap();
+        daemon.save_to_file(store).await.unwrap();
+        let base = snapshot(&daemon);
+
+        // the CLI renames task 1 and adds task 3
+        let cli = state();
+        cli.load_from_file(store).await.unwrap();
+        cli.tasks.get_mut(&1).unwrap().title = "Quarterly report".into();
+        cli.insert_task(task("Call")).await.unwrap();
+        cli.save_to_file(store).await.unwrap();
+
+        // meanwhile the daemon raised task 2's priority and generated an occurrence as task 3
+        daemon.tasks.get_mut(&2).unwrap().priority = Priority::High;
+        daemon.insert_task(task("Standup")).await.unwrap();
+        let current = snapshot(&daemon);
+
+        merge_from_store(&daemon, store, &base, &current).await.unwrap();
+        daemon.save_to_file(store).await.unwrap();
+        let saved = state();
+        saved.load_from_file(store).await.unwrap();
+        std::fs::remove_file(store).unwrap();
+
+        let titles: BTreeMap<usize, String> = snapshot(&saved).into_iter().map(|(id, task)| (id, task.title)).collect();
+        assert_eq!(titles, BTreeMap::from([(1, "Quarterly report".into()), (2, "Standup".into()), (3, "Call".into()), (4, "Standup".into())]));
+        assert_eq!(saved.tasks.get(&2).unwrap().priority, Priority::High);
+        assert_eq!(saved.next_id.load(Ordering::SeqCst), 5);
+    }
+}
@@ -14,6 +14,30 @@ pub enum TaskError {
     #[error("Invalid time: {0}")]
     InvalidTime(String),
     
+    #[error("Invalid recurrence rule: {0}")]
+    InvalidRecurrence(String),
+    
+    #[error("Task {0} has no upcoming recurrence")]
+    NotRecurring(usize),
+    
+    #[error("Task {0} has no reminder that went off")]
+    NoReminder(usize),
+    
+    #[error("Overlaps with task {id} '{title}'")]
+    Conflict { id: usize, title: String },
+    
+    #[error("Overlaps with the occurrence of series {series} '{title}' at {start}")]
+    UpcomingConflict { series: usize, title: String, start: chrono::DateTime<chrono::Utc> },
+    
+    #[error("Invalid filter: {0}")]
+    Filter(String),
+    
+    #[error("Configuration error: {0}")]
+    Config(String),
+    
+    #[error("Notification error: {0}")]
+    Notify(String),
+    
     #[error("PDF generation error: {0}")]
     PdfError(String),
     
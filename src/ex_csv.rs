@@ -1,4 +1,4 @@
-use crate::{error::TaskError, shared::Task};
+use crate::{error::TaskError, filter::Filter, shared::Task};
 use printpdf::{BuiltinFont, PdfDocument, Mm};
 use std::{io::BufWriter, fs::File};
 
@@ -16,28 +16,49 @@ impl Exportable {
         Exportable { tasks }
     }
 
-    pub async fn export_to_csv(&self, filename: &str) -> Result<(), TaskError> {
-        let tasks = self.tasks.iter();
+    // Tasks the filter selects (all of them without one), in ID order
+    fn matching(&self, filter: Option<&Filter>) -> Vec<Task> {
+        let mut tasks: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|entry| filter.is_none_or(|filter| filter.matches(entry.value())))
+            .map(|entry| entry.value().clone())
+            .collect();
+        tasks.sort_by_key(|task| task.id);
+        tasks
+    }
+
+    pub async fn export_to_csv(&self, filename: &str, filter: Option<&Filter>) -> Result<(), TaskError> {
+        let tasks = self.matching(filter);
         let mut wtr = Writer::from_path(filename)?;
         
-        wtr.write_record(&["ID", "Title", "Details", "Start", "End", "Recurring", "Frequency"])?;
+        wtr.write_record(["ID", "Title", "Details", "Start", "End", "Recurring", "Frequency", "Recurrence"])?;
         
         for task in tasks {
-            wtr.serialize(serde_json::to_value(task.value())?)?;
+            wtr.write_record([
+                task.id.to_string(),
+                task.title,
+                task.details,
+                task.start_time.to_rfc3339(),
+                task.end_time.to_rfc3339(),
+                task.is_recurring.to_string(),
+                task.frequency_minutes.map(|minutes| minutes.to_string()).unwrap_or_default(),
+                task.recurrence.as_ref().map(|rule| rule.to_string()).unwrap_or_default(),
+            ])?;
         }
         wtr.flush()?;
         Ok(())
     }
 
-    pub async fn export_to_json(&self, filename: &str) -> Result<(), TaskError> {
-        let tasks: Vec<_> = self.tasks.iter().map(|entry| entry.value().clone()).collect();
+    pub async fn export_to_json(&self, filename: &str, filter: Option<&Filter>) -> Result<(), TaskError> {
+        let tasks = self.matching(filter);
         let file = File::create(filename)?;
         to_writer(file, &tasks)?;
         Ok(())
     }
 
-    pub async fn export_to_pdf(&self, filename: &str) -> Result<(), TaskError> {
-        let tasks = self.tasks.iter();
+    pub async fn export_to_pdf(&self, filename: &str, filter: Option<&Filter>) -> Result<(), TaskError> {
+        let tasks = self.matching(filter);
         let (doc, page, layer) = PdfDocument::new("Todo Tasks", Mm(210.0), Mm(297.0), "Layer 1");
         let font = doc.add_builtin_font(BuiltinFont::Helvetica)
             .map_err(|e| TaskError::PdfError(e.to_string()))?;
@@ -47,14 +68,15 @@ impl Exportable {
         
         for task in tasks {
             let text = format!(
-                "ID: {}\nTitle: {}\nDetails: {}\nStart: {}\nEnd: {}\nRecurring: {}\nFrequency: {}",
+                "ID: {}\nTitle: {}\nDetails: {}\nStart: {}\nEnd: {}\nRecurring: {}\nFrequency: {}\nRecurrence: {}",
                 task.id,
                 task.title,
                 task.details,
                 task.start_time,
                 task.end_time,
                 task.is_recurring,
-                task.frequency_minutes.unwrap_or(0)
+                task.frequency_minutes.unwrap_or(0),
+                task.recurrence.as_ref().map(|r| r.to_string()).unwrap_or_default()
             );
             
             current_layer.use_text(text, 12.0, Mm(10.0), y_position, &font);
@@ -72,3 +94,59 @@ impl Exportable {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recurrence::Recurrence;
+    use chrono::{TimeZone, Utc};
+
+    fn exportable() -> Exportable {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        let tasks = Arc::new(DashMap::new());
+        let mut standup = Task::new(
+            "Standup".into(),
+            "Daily, sync".into(),
+            start,
+            start + chrono::Duration::minutes(15),
+            true,
+            None,
+            Some("FREQ=DAILY".parse::<Recurrence>().unwrap()),
+        );
+        standup.id = 1;
+        let mut review = Task::new("Review".into(), "".into(), start, start + chrono::Duration::hours(1), false, Some(60), None);
+        review.id = 2;
+        tasks.insert(1, standup);
+        tasks.insert(2, review);
+        Exportable::new(tasks)
+    }
+
+    #[tokio::test]
+    async fn csv_has_one_row_per_task_matching_the_header() {
+        let path = std::env::temp_dir().join(format!("todo_task_export_{}.csv", std::process::id()));
+        let filter = Filter::parse("title:standup", Utc::now(), crate::config::Zone::Fixed(chrono::FixedOffset::east_opt(0).unwrap())).unwrap();
+        exportable().export_to_csv(path.to_str().unwrap(), Some(&filter)).await.unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(reader.headers().unwrap().len(), 8);
+        let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(&rows[0][0], "1");
+        assert_eq!(&rows[0][2], "Daily, sync");
+        assert_eq!(&rows[0][3], "2030-01-01T09:00:00+00:00");
+        assert_eq!(&rows[0][5], "true");
+        assert_eq!(&rows[0][7], "FREQ=DAILY");
+    }
+
+    #[tokio::test]
+    async fn csv_without_filter_exports_every_task_in_id_order() {
+        let path = std::env::temp_dir().join(format!("todo_task_export_all_{}.csv", std::process::id()));
+        exportable().export_to_csv(path.to_str().unwrap(), None).await.unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let ids: Vec<String> = reader.records().map(|row| row.unwrap()[0].to_string()).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ids, ["1", "2"]);
+    }
+}
@@ -0,0 +1,467 @@
+use crate::config::Zone;
+use crate::error::TaskError;
+use crate::shared::{Priority, Task, TaskStatus};
+use crate::time_parse::parse_when;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use std::cmp::Ordering;
+
+/// A parsed filter expression such as
+/// `status:pending and (tag:ops or priority>=high) and start<+7d`.
+///
+/// Conditions are `field` `op` `value` with no spaces around the operator; values with spaces
+/// are double-quoted. Conditions combine with `and`, `or`, `not` and parentheses, `and`
+/// binding tighter than `or`. Relative times like `+7d` are resolved when the filter is parsed.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+    now: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Condition(Field, Op, Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    Title,
+    Details,
+    Notes,
+    Text,
+    Status,
+    Priority,
+    Tag,
+    Recurring,
+    Overdue,
+    Start,
+    End,
+    Deadline,
+    Due,
+}
+
+const FIELDS: &str = "id, title, details, notes, text, status, priority, tag, recurring, overdue, start, end, deadline, due";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// `:` — contains for text, has for tags, equals otherwise
+    Match,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(usize),
+    Text(String),
+    Status(TaskStatus),
+    Priority(Priority),
+    Bool(bool),
+    Time(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Condition { field: String, op: Op, value: String },
+}
+
+impl Filter {
+    pub fn parse(input: &str, now: DateTime<Utc>, zone: Zone) -> Result<Filter, TaskError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(TaskError::Filter("empty filter expression".to_string()));
+        }
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            now,
+            zone,
+        };
+        let expr = parser.or()?;
+        if let Some((token, column)) = parser.tokens.get(parser.index) {
+            return Err(TaskError::Filter(format!(
+                "unexpected {} at column {}",
+                describe(token),
+                column
+            )));
+        }
+        Ok(Filter { expr, now })
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.eval(&self.expr, task)
+    }
+
+    fn eval(&self, expr: &Expr, task: &Task) -> bool {
+        match expr {
+            Expr::And(lhs, rhs) => self.eval(lhs, task) && self.eval(rhs, task),
+            Expr::Or(lhs, rhs) => self.eval(lhs, task) || self.eval(rhs, task),
+            Expr::Not(inner) => !self.eval(inner, task),
+            Expr::Condition(field, op, value) => self.condition(*field, *op, value, task),
+        }
+    }
+
+    fn condition(&self, field: Field, op: Op, value: &Value, task: &Task) -> bool {
+        match (field, value) {
+            (Field::Id, Value::Number(id)) => compare(task.id, op, *id),
+            (Field::Title, Value::Text(text)) => text_matches(&task.title, op, text),
+            (Field::Details, Value::Text(text)) => text_matches(&task.details, op, text),
+            (Field::Notes, Value::Text(text)) => text_matches(&task.notes, op, text),
+            (Field::Text, Value::Text(text)) => {
+                let found = [&task.title, &task.details, &task.notes]
+                    .into_iter()
+                    .any(|field| text_matches(field, Op::Match, text));
+                if op == Op::Ne {
+                    !found
+                } else {
+                    found
+                }
+            }
+            (Field::Status, Value::Status(status)) => (task.status == *status) != (op == Op::Ne),
+            (Field::Priority, Value::Priority(priority)) => compare(task.priority, op, *priority),
+            (Field::Tag, Value::Text(tag)) => task.has_tag(tag) != (op == Op::Ne),
+            (Field::Recurring, Value::Bool(recurring)) => compare(task.series_id.is_some(), op, *recurring),
+            (Field::Overdue, Value::Bool(overdue)) => compare(task.is_overdue(self.now), op, *overdue),
+            (Field::Start, Value::Time(time)) => compare(task.start_time, op, *time),
+            (Field::End, Value::Time(time)) => compare(task.end_time, op, *time),
+            (Field::Deadline, Value::Time(time)) => task.deadline.is_some_and(|deadline| compare(deadline, op, *time)),
+            (Field::Due, Value::Time(time)) => compare(task.due(), op, *time),
+            _ => false,
+        }
+    }
+}
+
+fn compare<T: Ord>(lhs: T, op: Op, rhs: T) -> bool {
+    let ordering = lhs.cmp(&rhs);
+    match op {
+        Op::Match | Op::Eq => ordering == Ordering::Equal,
+        Op::Ne => ordering != Ordering::Equal,
+        Op::Lt => ordering == Ordering::Less,
+        Op::Le => ordering != Ordering::Greater,
+        Op::Gt => ordering == Ordering::Greater,
+        Op::Ge => ordering != Ordering::Less,
+    }
+}
+
+// `:` finds the text anywhere, `=`/`!=` compare the whole field; always case-insensitive
+fn text_matches(field: &str, op: Op, text: &str) -> bool {
+    let field = field.to_lowercase();
+    let text = text.to_lowercase();
+    match op {
+        Op::Match => field.contains(&text),
+        Op::Ne => field != text,
+        _ => field == text,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+        Token::And => "'and'".to_string(),
+        Token::Or => "'or'".to_string(),
+        Token::Not => "'not'".to_string(),
+        Token::Condition { field, .. } => format!("condition on '{}'", field),
+    }
+}
+
+// Splits the input into parentheses, keywords and `field op value` conditions,
+// each with the column it starts at (1-based)
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, TaskError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            tokens.push((if c == '(' { Token::Open } else { Token::Close }, column));
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        if word.is_empty() {
+            return Err(TaskError::Filter(format!("expected a field name at column {}", column)));
+        }
+
+        let op = match (chars.get(i), chars.get(i + 1)) {
+            (Some('<'), Some('=')) => Some((Op::Le, 2)),
+            (Some('>'), Some('=')) => Some((Op::Ge, 2)),
+            (Some('!'), Some('=')) => Some((Op::Ne, 2)),
+            (Some('<'), _) => Some((Op::Lt, 1)),
+            (Some('>'), _) => Some((Op::Gt, 1)),
+            (Some('='), _) => Some((Op::Eq, 1)),
+            (Some(':'), _) => Some((Op::Match, 1)),
+            _ => None,
+        };
+        let Some((op, width)) = op else {
+            let keyword = match word.to_ascii_lowercase().as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                _ => {
+                    return Err(TaskError::Filter(format!(
+                        "expected a condition like 'status:pending' at column {}, found '{}'",
+                        column, word
+                    )))
+                }
+            };
+            tokens.push((keyword, column));
+            continue;
+        };
+        i += width;
+
+        let value = if chars.get(i) == Some(&'"') {
+            let quote = i;
+            i += 1;
+            let value_start = i;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err(TaskError::Filter(format!("unterminated quote at column {}", quote + 1)));
+            }
+            let value: String = chars[value_start..i].iter().collect();
+            i += 1;
+            value
+        } else {
+            let value_start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+                i += 1;
+            }
+            chars[value_start..i].iter().collect()
+        };
+        if value.is_empty() {
+            return Err(TaskError::Filter(format!("missing value after '{}' at column {}", word, column)));
+        }
+        tokens.push((Token::Condition { field: word, op, value }, column));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    now: DateTime<Utc>,
+    zone: Zone,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.index).map_or(0, |(_, column)| *column)
+    }
+
+    fn or(&mut self) -> Result<Expr, TaskError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.index += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, TaskError> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.index += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, TaskError> {
+        let column = self.column();
+        let Some((token, _)) = self.tokens.get(self.index).cloned() else {
+            return Err(TaskError::Filter("expression ends too early, expected a condition".to_string()));
+        };
+        self.index += 1;
+        match token {
+            Token::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let expr = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(TaskError::Filter(format!("missing ')' for '(' at column {}", column)));
+                }
+                self.index += 1;
+                Ok(expr)
+            }
+            Token::Condition { field, op, value } => self.condition(&field, op, &value, column),
+            other => Err(TaskError::Filter(format!(
+                "expected a condition at column {}, found {}",
+                column,
+                describe(&other)
+            ))),
+        }
+    }
+
+    fn condition(&self, name: &str, op: Op, value: &str, column: usize) -> Result<Expr, TaskError> {
+        let field = match name.to_ascii_lowercase().as_str() {
+            "id" => Field::Id,
+            "title" => Field::Title,
+            "details" => Field::Details,
+            "notes" => Field::Notes,
+            "text" => Field::Text,
+            "status" => Field::Status,
+            "priority" => Field::Priority,
+            "tag" => Field::Tag,
+            "recurring" => Field::Recurring,
+            "overdue" => Field::Overdue,
+            "start" => Field::Start,
+            "end" => Field::End,
+            "deadline" => Field::Deadline,
+            "due" => Field::Due,
+            _ => {
+                return Err(TaskError::Filter(format!(
+                    "unknown field '{}' at column {}, expected one of: {}",
+                    name, column, FIELDS
+                )))
+            }
+        };
+
+        let ordered = matches!(
+            field,
+            Field::Id | Field::Priority | Field::Start | Field::End | Field::Deadline | Field::Due
+        );
+        if !ordered && matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge) {
+            return Err(TaskError::Filter(format!(
+                "'{}' at column {} can only be compared with ':', '=' or '!='",
+                name, column
+            )));
+        }
+
+        let invalid = |expected: &str| {
+            TaskError::Filter(format!(
+                "invalid value '{}' for '{}' at column {}, expected {}",
+                value, name, column, expected
+            ))
+        };
+        let value = match field {
+            Field::Id => Value::Number(value.parse().map_err(|_| invalid("a task ID"))?),
+            Field::Title | Field::Details | Field::Notes | Field::Text | Field::Tag => Value::Text(value.to_string()),
+            Field::Status => Value::Status(
+                TaskStatus::from_str(value, true).map_err(|_| invalid("pending, done or missed"))?,
+            ),
+            Field::Priority => Value::Priority(
+                Priority::from_str(value, true).map_err(|_| invalid("low, normal, high or urgent"))?,
+            ),
+            Field::Recurring | Field::Overdue => Value::Bool(match value.to_ascii_lowercase().as_str() {
+                "true" | "yes" => true,
+                "false" | "no" => false,
+                _ => return Err(invalid("true or false")),
+            }),
+            Field::Start | Field::End | Field::Deadline | Field::Due => Value::Time(
+                self.time(value)
+                    .ok_or_else(|| invalid("a time like now, today, next monday, +7d, -2h, 2025-03-01 or 2025-03-01T09:00:00Z"))?,
+            ),
+        };
+        Ok(Expr::Condition(field, op, value))
+    }
+
+    fn time(&self, value: &str) -> Option<DateTime<Utc>> {
+        parse_when(value, self.now, self.zone).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, 8, 0, 0).unwrap()
+    }
+
+    fn filter(input: &str) -> Result<Filter, TaskError> {
+        Filter::parse(input, now(), Zone::Fixed(FixedOffset::east_opt(0).unwrap()))
+    }
+
+    fn error(input: &str) -> String {
+        filter(input).unwrap_err().to_string()
+    }
+
+    fn task(title: &str, tags: &[&str], priority: Priority, start_hours: i64) -> Task {
+        let start = now() + chrono::Duration::hours(start_hours);
+        let mut task = Task::new(title.into(), String::new(), start, start + chrono::Duration::hours(1), false, None, None);
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task.priority = priority;
+        task
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let ops = task("Deploy", &["ops"], Priority::Low, 2);
+        let urgent = task("Call", &[], Priority::Urgent, 2);
+        let home = task("Shop", &["home"], Priority::Low, 2);
+
+        // tag:home or (tag:ops and priority>=high)
+        let loose = filter("tag:home or tag:ops and priority>=high").unwrap();
+        assert!(loose.matches(&home));
+        assert!(!loose.matches(&ops));
+
+        let grouped = filter("(tag:home or tag:ops) and priority>=high").unwrap();
+        assert!(!grouped.matches(&home));
+        assert!(!grouped.matches(&ops));
+        assert!(filter("(tag:ops or priority>=high) and start<+7d").unwrap().matches(&urgent));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_condition_or_group() {
+        let ops = task("Deploy", &["ops"], Priority::Low, 2);
+        assert!(!filter("not tag:ops and title:deploy").unwrap().matches(&ops));
+        assert!(filter("not (tag:ops and title:review)").unwrap().matches(&ops));
+        assert!(filter("NOT not tag:ops").unwrap().matches(&ops));
+    }
+
+    #[test]
+    fn conditions_compare_fields() {
+        let ops = task("Deploy release", &["ops"], Priority::High, 30);
+        assert!(filter("title:RELEASE").unwrap().matches(&ops));
+        assert!(!filter("title=release").unwrap().matches(&ops));
+        assert!(filter("title=\"deploy release\"").unwrap().matches(&ops));
+        assert!(filter("start>tomorrow and start<+2d").unwrap().matches(&ops));
+        assert!(filter("status:pending and priority!=low and recurring:false").unwrap().matches(&ops));
+        assert!(!filter("deadline<+7d").unwrap().matches(&ops));
+    }
+
+    #[test]
+    fn errors_point_at_the_column() {
+        assert_eq!(error("   "), "Invalid filter: empty filter expression");
+        assert!(error("status:pending and colour:red").contains("unknown field 'colour' at column 20"));
+        assert!(error("tag:ops or priority:whenever").contains("invalid value 'whenever' for 'priority' at column 12"));
+        assert!(error("(tag:ops or tag:home").contains("missing ')' for '(' at column 1"));
+        assert!(error("tag:ops tag:home").contains("unexpected condition on 'tag' at column 9"));
+        assert!(error("tag:ops and").contains("expression ends too early"));
+        assert!(error("tag:ops or and tag:home").contains("expected a condition at column 12, found 'and'"));
+        assert!(error("title:\"open").contains("unterminated quote at column 7"));
+        assert!(error("title: x").contains("missing value after 'title' at column 1"));
+        assert!(error("pending").contains("at column 1, found 'pending'"));
+        assert!(error("title<x").contains("'title' at column 1 can only be compared"));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::config::{Config, Zone};
+use crate::error::TaskError;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+// Give up looking for a working day after this many calendar days
+const MAX_SHIFT_DAYS: i64 = 366;
+
+/// What a recurrence does when an occurrence lands on a weekend or holiday
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum NonWorkingPolicy {
+    /// Keep the occurrence where the rule puts it
+    #[default]
+    Keep,
+    /// Drop the occurrence
+    Skip,
+    /// Move it to the next working day
+    Next,
+    /// Move it to the previous working day
+    Previous,
+}
+
+/// Working week plus public holidays, used to keep recurrences off non-working days
+#[derive(Debug, Clone)]
+pub struct WorkCalendar {
+    pub zone: Zone,
+    pub working_days: Vec<Weekday>,
+    pub holidays: HashSet<NaiveDate>,
+}
+
+impl Default for WorkCalendar {
+    fn default() -> Self {
+        let config = Config::default();
+        WorkCalendar {
+            zone: Zone::Local,
+            working_days: config.working_days,
+            holidays: HashSet::new(),
+        }
+    }
+}
+
+impl WorkCalendar {
+    pub fn from_config(config: &Config) -> Result<Self, TaskError> {
+        let holidays = match &config.holiday_file {
+            Some(path) => load_holidays(path)?,
+            None => HashSet::new(),
+        };
+        Ok(WorkCalendar {
+            zone: config.zone()?,
+            working_days: config.working_days.clone(),
+            holidays,
+        })
+    }
+
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        self.working_days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    /// Apply `policy` to an occurrence starting at `start`. Returns `None` if the occurrence
+    /// should be dropped, otherwise its (possibly shifted) start time, keeping the time of day.
+    pub fn adjust(&self, start: DateTime<Utc>, policy: NonWorkingPolicy) -> Option<DateTime<Utc>> {
+        let local = self.zone.to_local(start);
+        if policy == NonWorkingPolicy::Keep || self.is_working_day(local.date()) {
+            return Some(start);
+        }
+        let step = match policy {
+            NonWorkingPolicy::Next => Duration::days(1),
+            NonWorkingPolicy::Previous => Duration::days(-1),
+            _ => return None,
+        };
+        let mut date = local.date();
+        for _ in 0..MAX_SHIFT_DAYS {
+            date += step;
+            if self.is_working_day(date) {
+                return Some(self.zone.to_utc(date.and_time(local.time())));
+            }
+        }
+        None
+    }
+}
+
+/// Read holiday dates from a .ics calendar or a CSV file, picked by extension
+pub fn load_holidays(path: &str) -> Result<HashSet<NaiveDate>, TaskError> {
+    let is_ics = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ics"));
+    if is_ics {
+        Ok(parse_ics(&fs::read_to_string(path)?))
+    } else {
+        parse_csv(path)
+    }
+}
+
+// Collects the days covered by each VEVENT. All-day events use an exclusive DTEND,
+// so a one-day holiday has DTEND on the following day (or no DTEND at all).
+fn parse_ics(contents: &str) -> HashSet<NaiveDate> {
+    // Undo RFC 5545 line folding: continuation lines start with a space or tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut holidays = HashSet::new();
+    let mut start: Option<NaiveDate> = None;
+    let mut end: Option<NaiveDate> = None;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let property = name.split(';').next().unwrap_or_default().to_ascii_uppercase();
+        match property.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => {
+                start = None;
+                end = None;
+            }
+            "DTSTART" => start = parse_ics_date(value),
+            "DTEND" => end = parse_ics_date(value),
+            "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(first) = start {
+                    let last = end.filter(|e| *e > first).map(|e| e - Duration::days(1)).unwrap_or(first);
+                    holidays.extend(first.iter_days().take_while(|d| *d <= last));
+                }
+            }
+            _ => {}
+        }
+    }
+    holidays
+}
+
+fn parse_ics_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim().get(..8)?, "%Y%m%d").ok()
+}
+
+// One holiday per row with an ISO date (YYYY-MM-DD) in the first column;
+// rows that don't start with a date, such as a header, are ignored
+fn parse_csv(path: &str) -> Result<HashSet<NaiveDate>, TaskError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut holidays = HashSet::new();
+    for record in reader.records() {
+        let record = record?;
+        if let Some(date) = record
+            .get(0)
+            .and_then(|field| NaiveDate::parse_from_str(field.trim(), "%Y-%m-%d").ok())
+        {
+            holidays.insert(date);
+        }
+    }
+    Ok(holidays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    // 2030-01-05 and 06 are a weekend, the 7th and 8th holidays
+    fn calendar() -> WorkCalendar {
+        WorkCalendar {
+            zone: Zone::Fixed(FixedOffset::east_opt(3600).unwrap()),
+            holidays: HashSet::from([date(7), date(8)]),
+            ..WorkCalendar::default()
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2030, 1, day).unwrap()
+    }
+
+    // 09:00 local time
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, 8, 0, 0).unwrap()
+    }
+
+    fn temp_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("todo_task_{}_{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn shifts_past_weekends_and_consecutive_holidays() {
+        let calendar = calendar();
+        assert_eq!(calendar.adjust(at(5), NonWorkingPolicy::Keep), Some(at(5)));
+        assert_eq!(calendar.adjust(at(5), NonWorkingPolicy::Skip), None);
+        assert_eq!(calendar.adjust(at(5), NonWorkingPolicy::Next), Some(at(9)));
+        assert_eq!(calendar.adjust(at(8), NonWorkingPolicy::Previous), Some(at(4)));
+        for policy in [NonWorkingPolicy::Skip, NonWorkingPolicy::Next, NonWorkingPolicy::Previous] {
+            assert_eq!(calendar.adjust(at(9), policy), Some(at(9)));
+        }
+    }
+
+    #[test]
+    fn days_are_judged_in_the_configured_zone() {
+        // 23:30 UTC on Friday the 4th is already Saturday in UTC+1
+        let late = Utc.with_ymd_and_hms(2030, 1, 4, 23, 30, 0).unwrap();
+        assert_eq!(calendar().adjust(late, NonWorkingPolicy::Previous), Some(late - Duration::days(1)));
+    }
+
+    #[test]
+    fn reads_all_day_and_multi_day_ics_events() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\nSUMMARY:New Year\r\nDTSTART;VALUE=DATE:20300101\r\nDTEND;VALUE=DATE:20300102\r\nEND:VEVENT\r\n\
+                   BEGIN:VEVENT\r\nSUMMARY:Epiphany\r\nDTSTART;VALUE=DATE:20300106\r\nEND:VEVENT\r\n\
+                   BEGIN:VEVENT\r\nSUMMARY:Company\r\n  retreat\r\nDTSTART:20300220T000000Z\r\nDTEND;VALUE=DATE:20300222\r\nEND:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+        let path = temp_file("holidays.ics", ics);
+        let holidays = load_holidays(&path);
+        fs::remove_file(&path).unwrap();
+        let feb = |day| NaiveDate::from_ymd_opt(2030, 2, day).unwrap();
+        assert_eq!(holidays.unwrap(), HashSet::from([date(1), date(6), feb(20), feb(21)]));
+    }
+
+    #[test]
+    fn csv_rows_without_a_date_are_skipped() {
+        let path = temp_file("holidays.csv", "date,name\n2030-01-01,New Year\n2030-13-45,typo\nsoon\n 2030-01-06 ,\"Epiphany, observed\"\n");
+        let holidays = load_holidays(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(holidays.unwrap(), HashSet::from([date(1), date(6)]));
+        assert!(load_holidays("/nonexistent/holidays.csv").is_err());
+    }
+}
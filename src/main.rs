@@ -1,39 +1,102 @@
 mod shared;
 mod ex_csv;
 mod read_write;
+mod recurrence;
+mod config;
+mod holidays;
+mod daemon;
+mod scheduler;
+mod reminder;
+mod reminder_state;
+mod time_parse;
+mod notify;
+mod clock;
+mod simulate;
+mod conflicts;
+mod slots;
+mod quiet_hours;
+mod overdue;
+mod query;
+mod filter;
+mod search;
+mod output;
+mod agenda;
+mod calendar;
+mod stats;
+mod views;
 pub mod error;
 
-use crate::shared::{AppState, Task, TaskUpdate};
-use chrono::Utc;
+use crate::agenda::{AgendaRange, Occurrence};
+use crate::clock::{Clock, ShiftedClock, SystemClock};
+use crate::config::{Config, Zone};
+use crate::filter::Filter;
+use crate::error::TaskError;
+use crate::holidays::{NonWorkingPolicy, WorkCalendar};
+use crate::recurrence::Recurrence;
+use crate::reminder::Reminder;
+use crate::reminder_state::{ReminderKey, ReminderState};
+use crate::output::{OutputFormat, Record, Value};
+use crate::query::{SortKey, TaskQuery};
+use crate::shared::{AppState, CatchUpPolicy, EditScope, Priority, RecurFrom, Task, TaskStatus, TaskUpdate};
+use crate::slots::Within;
+use chrono::{DateTime, Datelike, SubsecRound, Utc};
 use clap::{Args, Parser, Subcommand};
+use std::path::Path;
 use std::sync::Arc;
-use tokio::time::sleep;
 use tokio::io;
 
+// Tasks are kept here between invocations
+const STORE_FILE: &str = "tasks.json";
+const CONFIG_FILE: &str = "todo_config.json";
+// Snoozed and acknowledged reminders, shared with the daemon
+const REMINDER_STATE_FILE: &str = "reminder_state.json";
+
 #[derive(Parser)]
 #[command(name = "Todo Task")]
 #[command(about = "A CLI tool for task management")]
-struct CLI {
+struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Run as if the current time were this, e.g. "2025-03-01T09:00:00Z" or "tomorrow 9am"; time keeps moving from there
+    #[arg(long, global = true)]
+    now: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Add a new Task
     Add(AddArgs), 
+    /// List tasks, optionally filtered, sorted and paged
+    List(ListArgs),
     /// List all tasks by title
     ListByTitle {
         /// Title of the tasks to list
         title: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Search titles, details and notes, best matches first; tolerates typos
+    Search {
+        /// Words to look for; every one must match
+        #[arg(required = true)]
+        terms: Vec<String>,
+        /// Maximum number of results to show
+        #[arg(long)]
+        limit: Option<usize>,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// List a task by ID
     ListByID {
         /// ID of the task to list
         id: usize,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Edit a task by its ID
     Edit(EditArgs),
+    /// Apply the same edit to every task matching a filter expression
+    BulkEdit(BulkEditArgs),
     /// Save tasks to a file
     SaveToFile { 
         /// File name to save tasks
@@ -48,22 +111,141 @@ enum Commands {
     ExportToCSV {
         /// File name for the exported CSV
         filename: String,
+        /// Only export tasks matching this filter expression
+        #[arg(long = "where")]
+        filter: Option<String>,
+        /// Only export tasks matching the filter of this saved view
+        #[arg(long, conflicts_with = "filter")]
+        view: Option<String>,
     },
     /// Export tasks to JSON
     ExportToJSON {
         /// File name for the exported JSON
         filename: String,
+        /// Only export tasks matching this filter expression
+        #[arg(long = "where")]
+        filter: Option<String>,
+        /// Only export tasks matching the filter of this saved view
+        #[arg(long, conflicts_with = "filter")]
+        view: Option<String>,
     },
     /// Export tasks to PDF
     ExportToPDF {
         /// File name for the exported PDF
         filename: String,
+        /// Only export tasks matching this filter expression
+        #[arg(long = "where")]
+        filter: Option<String>,
+        /// Only export tasks matching the filter of this saved view
+        #[arg(long, conflicts_with = "filter")]
+        view: Option<String>,
     },
     /// Delete a task by its ID
     Delete {
         /// ID of the task to delete
         id: usize,
     },
+    /// Mark a task as done
+    Done {
+        /// ID of the task to complete
+        id: usize,
+    },
+    /// Cancel the next upcoming occurrence of a recurring task
+    SkipNext {
+        /// ID of the recurring task
+        id: usize,
+    },
+    /// Show the tasks of today, tomorrow or the coming week, day by day
+    Agenda {
+        /// Which days to show
+        #[arg(value_enum, default_value_t = AgendaRange::Today)]
+        range: AgendaRange,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Run a saved view; `view save` stores one
+    View(ViewArgs),
+    /// Show a month or week grid with the tasks on each day
+    Calendar {
+        /// Month to show, e.g. "2025-03"; the current month by default
+        #[arg(long, value_parser = calendar::parse_month, conflicts_with = "week")]
+        month: Option<chrono::NaiveDate>,
+        /// Show one week instead: the current one, or the one containing this date
+        #[arg(long)]
+        week: Option<Option<chrono::NaiveDate>>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Show how many tasks were created and completed, how punctually, and where the time goes
+    Stats {
+        /// Number of calendar weeks to cover, ending with the current one
+        #[arg(long, default_value_t = 8)]
+        weeks: u32,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List pending tasks that are past their deadline or end time
+    Overdue {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List pending tasks that are scheduled at the same time
+    Conflicts {
+        /// Only overlaps ending after this time, e.g. "2025-03-01T09:00:00Z" or "monday"; defaults to now
+        #[arg(long)]
+        from: Option<String>,
+        /// Only overlaps starting before this time, e.g. "+7d" (optional)
+        #[arg(long)]
+        to: Option<String>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Find free time between tasks within working hours
+    FindSlot {
+        /// How long the slot must be, e.g. "90m" or "2h"
+        #[arg(long, value_parser = time_parse::parse_length)]
+        duration: chrono::Duration,
+        /// How far ahead to look
+        #[arg(long, value_enum, default_value_t = Within::Week)]
+        within: Within,
+        /// Maximum number of slots to show
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Remind again later about a reminder that went off
+    Snooze {
+        /// ID of the task
+        id: usize,
+        /// How long to wait before reminding again, e.g. "10m" or "1h"
+        #[arg(long = "for", default_value = "10m", value_parser = time_parse::parse_length)]
+        duration: chrono::Duration,
+        /// Which reminder to snooze ("start-5m", ...); the one that went off last by default
+        #[arg(long)]
+        reminder: Option<Reminder>,
+    },
+    /// Acknowledge a reminder so it doesn't go off again
+    Ack {
+        /// ID of the task
+        id: usize,
+        /// Which reminder to acknowledge ("start-5m", ...); the one that went off last by default
+        #[arg(long, conflicts_with = "all")]
+        reminder: Option<Reminder>,
+        /// Silence every remaining reminder of the task
+        #[arg(long)]
+        all: bool,
+    },
+    /// Run in the foreground, sending reminders and overdue alerts until stopped
+    Daemon,
+    /// Show what the daemon would do up to a time, without waiting or changing any task
+    Simulate {
+        /// End of the simulation, e.g. "2025-03-01T09:00:00Z" or "+3d"
+        #[arg(long)]
+        until: String,
+    },
+    /// Send a test notification through every configured notifier
+    NotifyTest,
 }
 
 #[derive(Args)]
@@ -72,140 +254,401 @@ struct AddArgs {
     title: String,
     /// Details of the task
     details: String,
-    /// Start time (ISO 8601 format, e.g., "2024-12-31T15:00:06")
-    start_time: String,
-    /// End time (ISO 8601 format, e.g., "2024-12-31T17:00:06")
-    end_time: String,
+    /// Start time, e.g. "2024-12-31T15:00:00Z", "tomorrow 3pm", "next monday 09:00" or "in 30 minutes"
+    #[arg(required_unless_present = "auto")]
+    start_time: Option<String>,
+    /// End time, in the same forms as the start time
+    #[arg(required_unless_present_any = ["auto", "length"])]
+    end_time: Option<String>,
+    /// How long the task takes, instead of an end time or for --auto (e.g. "45m" or "1 hour")
+    #[arg(long = "for", visible_alias = "duration", conflicts_with = "end_time", value_parser = time_parse::parse_length)]
+    length: Option<chrono::Duration>,
+    /// Place the task in the first free slot of length --for instead of giving start and end times
+    #[arg(long, requires = "length", conflicts_with_all = ["start_time", "end_time"])]
+    auto: bool,
+    /// How far ahead --auto looks for a slot
+    #[arg(long, requires = "auto", value_enum, default_value_t = Within::Week)]
+    within: Within,
     /// Whether the task is recurring
     #[arg(long)]
     recurring: bool,
     /// Frequency of recurrence in minutes (only for recurring tasks)
     #[arg(long, requires = "recurring")]
     frequency_minutes: Option<i64>,
+    /// RFC 5545 recurrence rule, e.g. "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1" (implies --recurring)
+    /// UNTIL and COUNT end the series, e.g. "FREQ=DAILY;COUNT=10"
+    #[arg(long, conflicts_with = "frequency_minutes")]
+    rrule: Option<Recurrence>,
+    /// Occurrence start time to exclude from the series, e.g. "friday 09:00" (repeatable)
+    #[arg(long = "exdate")]
+    exdates: Vec<String>,
+    /// How occurrences missed while nothing was running are handled
+    #[arg(long, value_enum, default_value_t = CatchUpPolicy::Next)]
+    catch_up: CatchUpPolicy,
+    /// Compute the next occurrence from the schedule or from when the task was marked done
+    #[arg(long, value_enum, default_value_t = RecurFrom::Schedule)]
+    recur_from: RecurFrom,
+    /// What to do with occurrences landing on weekends or holidays
+    #[arg(long, value_enum, default_value_t = NonWorkingPolicy::Keep)]
+    non_working: NonWorkingPolicy,
+    /// Deadline, in the same forms as the start time (optional)
+    #[arg(long)]
+    deadline: Option<String>,
+    /// Reminder relative to start, end or deadline ("start-5m", "end-2m", "deadline-1d")
+    /// or at an ISO 8601 time; repeatable, defaults come from the config
+    #[arg(long = "remind")]
+    reminders: Vec<Reminder>,
+    /// How important the task is
+    #[arg(long, value_enum, default_value_t = Priority::Normal)]
+    priority: Priority,
+    /// Label for the task (repeatable)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Longer notes kept with the task
+    #[arg(long, default_value = "")]
+    notes: String,
 }
 
 #[derive(Args)]
 struct EditArgs {
     /// ID of the task to edit
     id: usize,
+    #[command(flatten)]
+    update: UpdateArgs,
+    /// Which occurrences of a recurring series to edit
+    #[arg(long, value_enum, default_value_t = EditScope::This)]
+    scope: EditScope,
+}
+
+#[derive(Args)]
+struct BulkEditArgs {
+    /// Filter expression selecting the tasks to edit, e.g. "tag:ops and status:pending"
+    #[arg(long = "where")]
+    filter: String,
+    #[command(flatten)]
+    update: UpdateArgs,
+}
+
+/// Changes shared by `edit` and `bulk-edit`
+#[derive(Args)]
+struct UpdateArgs {
     /// New title (optional)
     #[arg(long)]
     title: Option<String>,
     /// New details (optional)
     #[arg(long)]
     details: Option<String>,
-    /// New start time (ISO 8601 format, optional)
+    /// New notes (optional)
+    #[arg(long)]
+    notes: Option<String>,
+    /// New start time, e.g. "2024-12-31T15:00:00Z", "tomorrow 3pm" or "+2h" (optional)
     #[arg(long)]
     start_time: Option<String>,
-    /// New end time (ISO 8601 format, optional)
+    /// New end time, in the same forms as the start time (optional)
     #[arg(long)]
     end_time: Option<String>,
+    /// New length, moving the end time to this long after the start (e.g. "45m")
+    #[arg(long = "for", conflicts_with = "end_time", value_parser = time_parse::parse_length)]
+    length: Option<chrono::Duration>,
     /// Set task as recurring (optional)
     #[arg(long)]
     recurring: Option<bool>,
     /// Frequency of recurrence in minutes (optional)
     #[arg(long)]
     frequency_minutes: Option<i64>,
+    /// New RFC 5545 recurrence rule (optional, implies --recurring true)
+    #[arg(long)]
+    rrule: Option<Recurrence>,
+    /// Occurrence start time to exclude from the series, e.g. "friday 09:00" (repeatable)
+    #[arg(long = "exdate")]
+    exdates: Vec<String>,
+    /// How occurrences missed while nothing was running are handled (optional)
+    #[arg(long, value_enum)]
+    catch_up: Option<CatchUpPolicy>,
+    /// Compute the next occurrence from the schedule or from completion (optional)
+    #[arg(long, value_enum)]
+    recur_from: Option<RecurFrom>,
+    /// What to do with occurrences landing on weekends or holidays (optional)
+    #[arg(long, value_enum)]
+    non_working: Option<NonWorkingPolicy>,
+    /// New deadline, in the same forms as the start time (optional)
+    #[arg(long)]
+    deadline: Option<String>,
+    /// Replace the task's reminders ("start-5m", "end-2m", "deadline-1d" or an ISO 8601 time; repeatable)
+    #[arg(long = "remind")]
+    reminders: Vec<Reminder>,
+    /// Remove the task's own reminders so the configured defaults apply
+    #[arg(long, conflicts_with = "reminders")]
+    default_reminders: bool,
+    /// New priority (optional)
+    #[arg(long, value_enum)]
+    priority: Option<Priority>,
+    /// Replace the task's tags (repeatable)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Remove all of the task's tags
+    #[arg(long, conflicts_with = "tags")]
+    no_tags: bool,
 }
 
-// Send reminder at 5 mins before start and 2 mins before end
-async fn schedule_reminders(task: Task, state: Arc<AppState>) {
-    let reminder_time_start = task.start_time - chrono::Duration::minutes(5);
-    let reminder_time_end = task.end_time - chrono::Duration::minutes(2);
-    let now = Utc::now();
+impl UpdateArgs {
+    fn into_update(self, now: DateTime<Utc>, zone: Zone) -> Result<TaskUpdate, TaskError> {
+        let when = |input: Option<String>| input.map(|input| time_parse::parse_when(&input, now, zone)).transpose();
 
-    // wait until 5 mins before start time
-    if reminder_time_start > now {
-        if let Ok(duration) = reminder_time_start.signed_duration_since(now).to_std() {
-            sleep(duration).await;
-            println!("Reminder: '{}' starts in 5 minutes!", task.title);
-        }
-    }
-
-    //wait until 2 mins before end time
-    if reminder_time_end > now {
-        if let Ok(duration) = reminder_time_end.signed_duration_since(now).to_std() {
-            sleep(duration).await;
-            println!("Reminder: '{}' ends in 2 minutes!", task.title);
-        }
+        Ok(TaskUpdate {
+            title: self.title,
+            details: self.details,
+            notes: self.notes,
+            start_time: when(self.start_time)?,
+            end_time: when(self.end_time)?,
+            length: self.length,
+            is_recurring: self.recurring,
+            frequency_minutes: self.frequency_minutes,
+            recurrence: self.rrule,
+            exdates: self.exdates.iter().map(|input| time_parse::parse_when(input, now, zone)).collect::<Result<_, _>>()?,
+            catch_up: self.catch_up,
+            recur_from: self.recur_from,
+            non_working: self.non_working,
+            deadline: when(self.deadline)?,
+            reminders: if self.default_reminders {
+                Some(Vec::new())
+            } else {
+                Some(self.reminders).filter(|r| !r.is_empty())
+            },
+            priority: self.priority,
+            tags: if self.no_tags {
+                Some(Vec::new())
+            } else {
+                Some(self.tags).filter(|t| !t.is_empty())
+            },
+        })
     }
+}
 
-    // cloning the title field to reuse it after move
-    
-    let task_title = task.title.clone(); // clone the title
-    // mark task as completed
-    println!("Task '{}' is complete", task_title);
-
-    // if the task is a recurring, schedule the next instance
-    if task.is_recurring {
-        if let Some(frequency) = task.frequency_minutes {
-            let next_task = Task {
-                id: 0,
-                title: task.title.clone(),
-                details: task.details.clone(),
-                start_time: task.start_time + chrono::Duration::minutes(frequency),
-                end_time: task.end_time + chrono::Duration::minutes(frequency),
-                is_recurring: true,
-                frequency_minutes: Some(frequency),
-            };
-
-            // Add the next task to the state
-            let task_id = state.add_task(next_task.clone()).await;
-            println!("Next recurring task scheduled with ID: {:?}", task_id);
- 
-            // Spawn a task to schedule the next reminder
-            let state_clone = Arc::clone(&state);
+#[derive(Args)]
+struct ListArgs {
+    /// Only tasks ending after this time, e.g. "2025-03-01T09:00:00Z" or "today"
+    #[arg(long)]
+    from: Option<String>,
+    /// Only tasks starting before this time, e.g. "next monday"
+    #[arg(long)]
+    to: Option<String>,
+    /// Only tasks with this status
+    #[arg(long, value_enum)]
+    status: Option<TaskStatus>,
+    /// Only tasks that are (true) or aren't (false) part of a recurring series
+    #[arg(long)]
+    recurring: Option<bool>,
+    /// Only tasks with this tag (repeatable; all must match)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Only tasks whose title, details or notes contain this text
+    #[arg(long)]
+    text: Option<String>,
+    /// Order of the tasks
+    #[arg(long, value_enum, default_value_t = SortKey::Start)]
+    sort: SortKey,
+    /// Reverse the order
+    #[arg(long)]
+    reverse: bool,
+    /// Number of matching tasks to skip
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+    /// Maximum number of tasks to show
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Only tasks matching this filter expression, e.g. "status:pending and priority>=high"
+    #[arg(long = "where")]
+    filter: Option<String>,
+    /// Only print how many tasks match
+    #[arg(long)]
+    count: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
 
-            tokio::task::spawn_blocking(move || {
-                let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-                rt.block_on(async move {
-                    schedule_reminders(next_task, state_clone).await;
-                });
-            }); 
-        }
-    }
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ViewArgs {
+    #[command(subcommand)]
+    action: Option<ViewAction>,
+    /// Name of the saved view to run; lists the views when left out
+    name: Option<String>,
 }
 
+#[derive(Subcommand)]
+enum ViewAction {
+    /// Save a filter, sort order and output format under a name, replacing any view of that name
+    Save {
+        /// Name to run the view by
+        name: String,
+        /// Filter expression, e.g. "tag:ops and start<+7d"
+        #[arg(long = "where")]
+        filter: Option<String>,
+        /// Order of the tasks
+        #[arg(long, value_enum, default_value_t = SortKey::Start)]
+        sort: SortKey,
+        /// Reverse the order
+        #[arg(long)]
+        reverse: bool,
+        /// Maximum number of tasks to show
+        #[arg(long)]
+        limit: Option<usize>,
+        /// How to print the tasks
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+    },
+    /// List the saved views
+    List {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Delete a saved view
+    Delete {
+        /// Name of the view
+        name: String,
+    },
+}
 
+/// Output format shared by the read commands
+#[derive(Args)]
+struct OutputArgs {
+    /// How to print the results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+}
 
 // Main Application ENtry
 #[tokio::main]
 async fn main() -> Result<(), io::Error> {
     let done_folder = "tasks_done".to_string();
-    let state = Arc::new(AppState::new(done_folder));
-    let cli = CLI::parse();
+    let config = Config::load(CONFIG_FILE).map_err(std::io::Error::other)?;
+    let calendar = WorkCalendar::from_config(&config).map_err(std::io::Error::other)?;
+    let mut state = Arc::new(AppState::new(done_folder, calendar, config.conflict_policy));
+    let cli = Cli::parse();
+    let clock: Arc<dyn Clock> = match cli.now.as_deref().map(|now| time_parse::parse_when(now, Utc::now(), state.calendar.zone)) {
+        Some(Ok(now)) => Arc::new(ShiftedClock::starting_at(now)),
+        Some(Err(err)) => {
+            eprintln!("Error: {}", err);
+            return Ok(());
+        }
+        None => Arc::new(SystemClock),
+    };
+
+    if Path::new(STORE_FILE).exists() {
+        state.load_from_file(STORE_FILE).await.map_err(std::io::Error::other)?;
+    }
+    let mutates_store = matches!(
+        cli.command,
+        Commands::Add(_)
+            | Commands::Edit(_)
+            | Commands::BulkEdit(_)
+            | Commands::Delete { .. }
+            | Commands::Done { .. }
+            | Commands::LoadFromFile { .. }
+            | Commands::SkipNext { .. }
+    );
+    let is_daemon = matches!(cli.command, Commands::Daemon);
+    // a simulation catches up on its own copy of the tasks
+    if !matches!(cli.command, Commands::Simulate { .. }) {
+        if mutates_store || is_daemon {
+            let caught_up = state.catch_up_recurrences(clock.now()).await.map_err(std::io::Error::other)?;
+            for report in &caught_up {
+                println!("{}", report);
+            }
+            // saved straight away, as the daemon writes the store on its own from here
+            if !caught_up.is_empty() {
+                state.save_to_file(STORE_FILE).await.map_err(std::io::Error::other)?;
+            }
+        } else {
+            // Read-only commands see missed occurrences caught up in a copy that is never saved
+            let copy = state.detached();
+            copy.catch_up_recurrences(clock.now()).await.map_err(std::io::Error::other)?;
+            state = Arc::new(copy);
+        }
+    }
 
     match cli.command {
-        Commands::ExportToCSV { filename } => {
-            state.export_to_csv(&filename)
-                .await
-                .expect("Failed to export tasks to CSV");
+        Commands::ExportToCSV { filename, filter, view } => {
+            let filter = match view {
+                Some(name) => saved_view(&config, &name).map(|view| view.filter.clone()),
+                None => Some(filter),
+            };
+            let Some(filter) = filter.and_then(|filter| parse_filter(filter, clock.now(), state.calendar.zone)) else { return Ok(()) };
+            if let Err(e) = state.export_to_csv(&filename, filter.as_ref()).await {
+                eprintln!("Failed to export tasks to CSV: {}", e);
+            }
         }
-        Commands::ExportToJSON { filename } => {
-            state.export_to_json(&filename)
-                .await
-                .expect("Failed to export tasks to JSON");
+        Commands::ExportToJSON { filename, filter, view } => {
+            let filter = match view {
+                Some(name) => saved_view(&config, &name).map(|view| view.filter.clone()),
+                None => Some(filter),
+            };
+            let Some(filter) = filter.and_then(|filter| parse_filter(filter, clock.now(), state.calendar.zone)) else { return Ok(()) };
+            if let Err(e) = state.export_to_json(&filename, filter.as_ref()).await {
+                eprintln!("Failed to export tasks to JSON: {}", e);
+            }
         }
-        Commands::ExportToPDF { filename } => {
-            state.export_to_pdf(&filename)
-                .await
-                .expect("Failed to export tasks to PDF"); 
+        Commands::ExportToPDF { filename, filter, view } => {
+            let filter = match view {
+                Some(name) => saved_view(&config, &name).map(|view| view.filter.clone()),
+                None => Some(filter),
+            };
+            let Some(filter) = filter.and_then(|filter| parse_filter(filter, clock.now(), state.calendar.zone)) else { return Ok(()) };
+            if let Err(e) = state.export_to_pdf(&filename, filter.as_ref()).await {
+                eprintln!("Failed to export tasks to PDF: {}", e);
+            }
         }
         Commands::SaveToFile { filename } => {
-            state.save_to_file(&filename).await.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            state.save_to_file(&filename).await.map_err(std::io::Error::other)?;
         }
         Commands::LoadFromFile { filename } => {
-            state.load_from_file(&filename).await.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            state.load_from_file(&filename).await.map_err(std::io::Error::other)?;
         }
         Commands::Add(args) => {
-            let start_time = chrono::DateTime::parse_from_rfc3339(&args.start_time)
-                .expect("Invalid start time format")
-                .with_timezone(&Utc);
-            let end_time = chrono::DateTime::parse_from_rfc3339(&args.end_time)
-                .expect("Invalid end time format")
-                .with_timezone(&Utc);
+            let now = clock.now();
+            let zone = state.calendar.zone;
+            let when = |input: &str| time_parse::parse_when(input, now, zone);
+            let parsed = args.deadline.as_deref().map(when).transpose().and_then(|deadline| {
+                let exdates = args.exdates.iter().map(|input| when(input)).collect::<Result<Vec<_>, _>>()?;
+                Ok((deadline, exdates))
+            });
+            let (deadline, exdates) = match parsed {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    return Ok(());
+                }
+            };
+            let (start_time, end_time) = match (&args.start_time, &args.end_time, args.length) {
+                (Some(start), end, length) => {
+                    let times = when(start).and_then(|start| match (end, length) {
+                        (Some(end), _) => Ok((start, when(end)?)),
+                        (None, Some(length)) => Ok((start, start + length)),
+                        (None, None) => unreachable!("clap requires an end time or --for"),
+                    });
+                    match times {
+                        Ok(times) => times,
+                        Err(err) => {
+                            eprintln!("Error: {}", err);
+                            return Ok(());
+                        }
+                    }
+                }
+                (None, _, Some(duration)) => {
+                    let (from, to) = args.within.range(clock.now(), state.calendar.zone);
+                    let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+                    let slots = slots::free_slots(&tasks, &state.calendar, &config.working_hours, from, to, duration);
+                    let Some((start, _)) = slots.first() else {
+                        eprintln!("Error: No free slot of {} found.", time_parse::format_duration(duration));
+                        return Ok(());
+                    };
+                    (*start, *start + duration)
+                }
+                (None, _, None) => unreachable!("clap requires a start time or --auto with --for"),
+            };
 
-            if start_time <= Utc::now() {
+            if start_time <= clock.now() {
                 eprintln!("Error: Start time must be in the future.");
                 return Ok(());
             }
@@ -214,34 +657,88 @@ async fn main() -> Result<(), io::Error> {
                 return Ok(());
             }
 
-            let task = Task::new(
+            let mut task = Task::new(
                 args.title,
                 args.details,
                 start_time,
                 end_time,
-                args.recurring,
+                args.recurring || args.rrule.is_some(),
                 args.frequency_minutes,
+                args.rrule,
             );
+            task.exdates = exdates;
+            task.catch_up = args.catch_up;
+            task.recur_from = args.recur_from;
+            task.non_working = args.non_working;
+            task.deadline = deadline;
+            task.reminders = args.reminders;
+            task.priority = args.priority;
+            task.tags = args.tags;
+            task.notes = args.notes;
+            task.created_at = Some(clock.now().trunc_subsecs(0));
 
-            let task_id = state.add_task(task.clone()).await;
-            println!("Task '{}' added with ID: {:?}", task.title, task_id);
-
-            tokio::spawn(schedule_reminders(task, Arc::clone(&state)));
-            
+            match state.add_task(task.clone()).await {
+                Ok(task_id) => println!("Task '{}' added with ID: {}", task.title, task_id),
+                Err(err) => eprintln!("Error adding task '{}': {}", task.title, err),
+            }
         }
-        Commands::ListByTitle { title } => {
-            let tasks = state.list_tasks_by_title(&title).await;
-            if tasks.is_empty() {
-                println!("No tasks available to display.");
+        Commands::List(args) => {
+            let Some(filter) = parse_filter(args.filter, clock.now(), state.calendar.zone) else { return Ok(()) };
+            let Some(from) = parse_time(args.from, clock.now(), state.calendar.zone) else { return Ok(()) };
+            let Some(to) = parse_time(args.to, clock.now(), state.calendar.zone) else { return Ok(()) };
+            let query = TaskQuery {
+                from,
+                to,
+                status: args.status,
+                recurring: args.recurring,
+                tags: args.tags,
+                text: args.text,
+                sort: args.sort,
+                reverse: args.reverse,
+                offset: args.offset,
+                limit: args.limit,
+                filter,
+            };
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            if args.count {
+                println!("{}", query.filter(&tasks).len());
             } else {
-                for task in tasks {
-                    println!("{:?}", task);
-                }
+                let records: Vec<Record> = query.run(&tasks).iter().map(output::task_record).collect();
+                output::print(args.output.output, state.calendar.zone, &records, "No tasks available to display.").map_err(std::io::Error::other)?;
             }
         }
-        Commands::ListByID { id } => {
+        Commands::ListByTitle { title, output } => {
+            let records: Vec<Record> = state.list_tasks_by_title(&title).await.iter().map(output::task_record).collect();
+            output::print(output.output, state.calendar.zone, &records, "No tasks available to display.").map_err(std::io::Error::other)?;
+        }
+        Commands::Search { terms, limit, output } => {
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let records: Vec<Record> = search::search(&tasks, &terms.join(" "))
+                .into_iter()
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|hit| {
+                    let mut matches = Vec::new();
+                    for (field, pieces) in hit.fragments {
+                        if !matches.is_empty() {
+                            matches.push(("  ".to_string(), false));
+                        }
+                        matches.push((format!("{}: ", field.name()), false));
+                        matches.extend(pieces);
+                    }
+                    Record::new()
+                        .field("id", Value::Int(hit.task.id as i64))
+                        .field("score", Value::Float(hit.score))
+                        .field("start", Value::Time(hit.task.start_time))
+                        .field("status", Value::Text(hit.task.status.to_string()))
+                        .field("title", Value::Text(hit.task.title.clone()))
+                        .field("matches", Value::Marked(matches))
+                })
+                .collect();
+            output::print(output.output, state.calendar.zone, &records, &format!("No tasks match '{}'.", terms.join(" "))).map_err(std::io::Error::other)?;
+        }
+        Commands::ListByID { id, output } => {
             if let Some(task) = state.list_tasks_by_id(id).await {
-                println!("{:?}", task);
+                output::print(output.output, state.calendar.zone, &[output::task_record(&task)], "").map_err(std::io::Error::other)?;
             } else {
                 println!("Task with ID {} not found.", id);
             }
@@ -252,45 +749,307 @@ async fn main() -> Result<(), io::Error> {
                 Err(err) => eprintln!("Error deleting task {}: {}", id, err),
             }
         }
-        Commands::Edit(EditArgs {
-            id,
-            title,
-            details,
-            start_time,
-            end_time,
-            recurring,
-            frequency_minutes,
-        }) => {
-            let parsed_start_time = if let Some(time_str) = start_time {
-                Some(chrono::DateTime::parse_from_rfc3339(&time_str)
-                    .expect("Invalid start time format")
-                    .with_timezone(&chrono::Utc))
-            } else {
-                None
+        Commands::Edit(EditArgs { id, update, scope }) => {
+            let task_update = match update.into_update(clock.now(), state.calendar.zone) {
+                Ok(task_update) => task_update,
+                Err(err) => {
+                    eprintln!("Error updating task {}: {}", id, err);
+                    return Ok(());
+                }
             };
 
-            let parsed_end_time = if let Some(time_str) = end_time {
-                Some(chrono::DateTime::parse_from_rfc3339(&time_str)
-                    .expect("Invalid end time format")
-                    .with_timezone(&chrono::Utc))
+            match state.edit_series(id, task_update, scope).await {
+                Ok(updated) if updated.len() > 1 => {
+                    println!("Task {} and {} other occurrence(s) updated successfully.", id, updated.len() - 1)
+                }
+                Ok(_) => println!("Task {} updated successfully.", id),
+                Err(err) => eprintln!("Error updating task {}: {}", id, err),
+            }
+        }
+        Commands::BulkEdit(BulkEditArgs { filter, update }) => {
+            let Some(Some(filter)) = parse_filter(Some(filter), clock.now(), state.calendar.zone) else { return Ok(()) };
+            let mut ids: Vec<usize> = state.tasks.iter().filter(|entry| filter.matches(entry.value())).map(|entry| *entry.key()).collect();
+            ids.sort_unstable();
+            let task_update = match update.into_update(clock.now(), state.calendar.zone) {
+                Ok(task_update) => task_update,
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    return Ok(());
+                }
+            };
+            let mut updated = 0;
+            for id in ids {
+                match state.edit_task(id, task_update.clone()).await {
+                    Ok(()) => updated += 1,
+                    Err(err) => eprintln!("Error updating task {}: {}", id, err),
+                }
+            }
+            println!("{} task(s) updated successfully.", updated);
+        }
+        Commands::Done { id } => {
+            match state.complete_task(id, clock.now()).await {
+                Ok(Some((next_id, start))) => {
+                    println!("Task {} marked as done. Next occurrence is task {} at {}.", id, next_id, start)
+                }
+                Ok(None) => println!("Task {} marked as done.", id),
+                Err(err) => eprintln!("Error completing task {}: {}", id, err),
+            }
+        }
+        Commands::Daemon => {
+            daemon::run(Arc::clone(&state), config.clone(), Arc::clone(&clock), STORE_FILE, REMINDER_STATE_FILE).await.map_err(std::io::Error::other)?;
+        }
+        Commands::Agenda { range, output } => {
+            let now = clock.now();
+            let zone = state.calendar.zone;
+            let days = range.days(now, zone);
+            let (from, _) = calendar::bounds(days[0], zone);
+            let (_, to) = calendar::bounds(days[days.len() - 1], zone);
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let occurrences = agenda::occurrences(&tasks, &state.calendar, from, to);
+            if output.output == OutputFormat::Table {
+                print!("{}", agenda::render(&occurrences, &days, zone, now, output::use_colour()));
             } else {
-                None
+                let records: Vec<Record> = occurrences.iter().map(agenda::record).collect();
+                output::print(output.output, state.calendar.zone, &records, "").map_err(std::io::Error::other)?;
+            }
+        }
+        Commands::View(ViewArgs { action, name }) => match (action, name) {
+            (Some(ViewAction::Save { name, filter, sort, reverse, limit, output }), _) => {
+                let view = views::View { filter, sort, reverse, limit, output };
+                if let Err(err) = view.validate(&name, clock.now(), state.calendar.zone) {
+                    eprintln!("Error saving view '{}': {}", name, err);
+                    return Ok(());
+                }
+                let mut updated = config.clone();
+                let replaced = updated.views.insert(name.clone(), view).is_some();
+                updated.save(CONFIG_FILE).map_err(std::io::Error::other)?;
+                println!("View '{}' {}.", name, if replaced { "updated" } else { "saved" });
+            }
+            (Some(ViewAction::Delete { name }), _) => {
+                let mut updated = config.clone();
+                if updated.views.remove(&name).is_none() {
+                    eprintln!("Error: no view named '{}'", name);
+                    return Ok(());
+                }
+                updated.save(CONFIG_FILE).map_err(std::io::Error::other)?;
+                println!("View '{}' deleted.", name);
+            }
+            (Some(ViewAction::List { output }), _) => print_views(&config, output.output, state.calendar.zone)?,
+            (None, None) => print_views(&config, OutputFormat::Table, state.calendar.zone)?,
+            (None, Some(name)) => {
+                let Some(view) = saved_view(&config, &name) else { return Ok(()) };
+                let query = match view.query(clock.now(), state.calendar.zone) {
+                    Ok(query) => query,
+                    Err(err) => {
+                        eprintln!("Error running view '{}': {}", name, err);
+                        return Ok(());
+                    }
+                };
+                let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+                let records: Vec<Record> = query.run(&tasks).iter().map(output::task_record).collect();
+                output::print(view.output, state.calendar.zone, &records, "No tasks available to display.").map_err(std::io::Error::other)?;
+            }
+        },
+        Commands::Calendar { month, week, output } => {
+            let zone = state.calendar.zone;
+            let today = zone.to_local(clock.now()).date();
+            let span = match (month, week) {
+                (_, Some(day)) => calendar::Span::Week(day.unwrap_or(today)),
+                (Some(first), None) => calendar::Span::Month(first),
+                (None, None) => calendar::Span::Month(today.with_day(1).unwrap_or(today)),
             };
-
-            let task_update = TaskUpdate {
-                title,
-                details,
-                start_time: parsed_start_time,
-                end_time: parsed_end_time,
-                is_recurring: recurring,
-                frequency_minutes,
+            let dates = span.days();
+            let (from, _) = calendar::bounds(dates[0], zone);
+            let (_, to) = calendar::bounds(dates[dates.len() - 1], zone);
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let occurrences = agenda::occurrences(&tasks, &state.calendar, from, to);
+            let capacity = config.working_hours.end - config.working_hours.start;
+            let days = calendar::days(&occurrences, &dates, zone, capacity);
+            if output.output == OutputFormat::Table {
+                print!("{}", calendar::render(&days, span, zone, today, output::use_colour()));
+            } else {
+                let records: Vec<Record> = days.iter().map(calendar::record).collect();
+                output::print(output.output, state.calendar.zone, &records, "").map_err(std::io::Error::other)?;
+            }
+        }
+        Commands::Stats { weeks, output } => {
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let report = stats::compute(&tasks, weeks, clock.now(), state.calendar.zone);
+            stats::print(&report, output.output, state.calendar.zone).map_err(std::io::Error::other)?;
+        }
+        Commands::Overdue { output } => {
+            let now = clock.now();
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let records: Vec<Record> = overdue::overdue_tasks(&tasks, now)
+                .iter()
+                .map(|task| {
+                    Record::new()
+                        .field("id", Value::Int(task.id as i64))
+                        .field("due", Value::Time(task.due()))
+                        .field("late", Value::Text(time_parse::humanize_duration(now - task.due())))
+                        .field("priority", Value::Text(task.priority.to_string()))
+                        .field("title", Value::Text(task.title.clone()))
+                })
+                .collect();
+            output::print(output.output, state.calendar.zone, &records, "No overdue tasks.").map_err(std::io::Error::other)?;
+        }
+        Commands::Conflicts { from, to, output } => {
+            let Some(from) = parse_time(from, clock.now(), state.calendar.zone) else { return Ok(()) };
+            let Some(to) = parse_time(to, clock.now(), state.calendar.zone) else { return Ok(()) };
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let records: Vec<Record> = conflicts::find_conflicts(&tasks, &state.calendar, from.unwrap_or_else(|| clock.now()), to)
+                .iter()
+                .map(|(first, second)| {
+                    // Occurrences a series hasn't created yet have no ID
+                    let id = |occurrence: &Occurrence| {
+                        if occurrence.projected { Value::Null } else { Value::Int(occurrence.task.id as i64) }
+                    };
+                    let title = |occurrence: &Occurrence| {
+                        let title = &occurrence.task.title;
+                        Value::Text(if occurrence.projected { format!("{} (upcoming)", title) } else { title.clone() })
+                    };
+                    Record::new()
+                        .field("from", Value::Time(first.task.start_time.max(second.task.start_time)))
+                        .field("to", Value::Time(first.task.end_time.min(second.task.end_time)))
+                        .field("id", id(first))
+                        .field("title", title(first))
+                        .field("other_id", id(second))
+                        .field("other_title", title(second))
+                })
+                .collect();
+            output::print(output.output, state.calendar.zone, &records, "No conflicts found.").map_err(std::io::Error::other)?;
+        }
+        Commands::FindSlot { duration, within, limit, output } => {
+            let (from, to) = within.range(clock.now(), state.calendar.zone);
+            let tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+            let records: Vec<Record> = slots::free_slots(&tasks, &state.calendar, &config.working_hours, from, to, duration)
+                .into_iter()
+                .take(limit)
+                .map(|(start, end)| {
+                    Record::new()
+                        .field("start", Value::Time(start))
+                        .field("end", Value::Time(end))
+                        .field("free", Value::Text(time_parse::format_duration(end - start)))
+                })
+                .collect();
+            let empty = format!("No free slot of {} found.", time_parse::format_duration(duration));
+            output::print(output.output, state.calendar.zone, &records, &empty).map_err(std::io::Error::other)?;
+        }
+        Commands::Snooze { id, duration, reminder } => {
+            let Some(task) = state.list_tasks_by_id(id).await else {
+                eprintln!("Error snoozing reminder of task {}: {}", id, TaskError::NotFound(id));
+                return Ok(());
             };
-
-            match state.edit_task(id, task_update).await {
-                Ok(_) => println!("Task {} updated successfully.", id),
-                Err(err) => eprintln!("Error updating task {}: {}", id, err),
+            let mut reminder_state = ReminderState::load(REMINDER_STATE_FILE).map_err(std::io::Error::other)?;
+            let now = clock.now();
+            match reminder_state.target(&task, config.reminders_for(&task), reminder.as_ref(), now) {
+                Ok(key) => {
+                    let until = (now + duration).trunc_subsecs(0);
+                    println!("Reminder '{}' of task {} snoozed until {}.", key.reminder, id, until);
+                    reminder_state.snooze(key, until);
+                    reminder_state.prune(&state.tasks);
+                    reminder_state.save(REMINDER_STATE_FILE).map_err(std::io::Error::other)?;
+                }
+                Err(err) => eprintln!("Error snoozing reminder of task {}: {}", id, err),
             }
         }
+        Commands::Ack { id, reminder, all } => {
+            let Some(task) = state.list_tasks_by_id(id).await else {
+                eprintln!("Error acknowledging reminder of task {}: {}", id, TaskError::NotFound(id));
+                return Ok(());
+            };
+            let mut reminder_state = ReminderState::load(REMINDER_STATE_FILE).map_err(std::io::Error::other)?;
+            let reminders = config.reminders_for(&task);
+            let keys = if all {
+                Ok(reminders.iter().filter_map(|reminder| ReminderKey::new(&task, reminder)).collect())
+            } else {
+                reminder_state.target(&task, reminders, reminder.as_ref(), clock.now()).map(|key| vec![key])
+            };
+            match keys {
+                Ok(keys) => {
+                    for key in keys {
+                        println!("Reminder '{}' of task {} acknowledged.", key.reminder, id);
+                        reminder_state.ack(key);
+                    }
+                    reminder_state.prune(&state.tasks);
+                    reminder_state.save(REMINDER_STATE_FILE).map_err(std::io::Error::other)?;
+                }
+                Err(err) => eprintln!("Error acknowledging reminder of task {}: {}", id, err),
+            }
+        }
+        Commands::Simulate { until } => {
+            let Some(Some(until)) = parse_time(Some(until), clock.now(), state.calendar.zone) else { return Ok(()) };
+            let reminder_state = ReminderState::load(REMINDER_STATE_FILE).map_err(std::io::Error::other)?;
+            if let Err(err) = simulate::run(&state, config.clone(), reminder_state, clock.now(), until).await {
+                eprintln!("Error running simulation: {}", err);
+            }
+        }
+        Commands::NotifyTest => {
+            let notifiers = notify::build_notifiers(&config.notifiers).map_err(std::io::Error::other)?;
+            let now = clock.now();
+            let sample = Task::new(
+                "Test notification".to_string(),
+                "Sent by notify-test".to_string(),
+                now,
+                now + chrono::Duration::minutes(30),
+                false,
+                None,
+                None,
+            );
+            let notification = notify::Notification {
+                event: "test".to_string(),
+                message: "Reminder: 'Test notification' starts now!".to_string(),
+                at: now,
+                task: sample,
+            };
+            notify::dispatch(&notifiers, notification).await;
+            println!("Test notification sent to {} notifier(s).", notifiers.len());
+        }
+        Commands::SkipNext { id } => {
+            match state.skip_next(id, clock.now()).await {
+                Ok(skipped) => println!("Skipped occurrence of task {} at {}.", id, skipped),
+                Err(err) => eprintln!("Error skipping occurrence of task {}: {}", id, err),
+            }
+        }
+    }
+
+    if mutates_store {
+        state.save_to_file(STORE_FILE).await.map_err(std::io::Error::other)?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+// Parses an optional `--where` expression; `None` once the error has been reported
+fn parse_filter(filter: Option<String>, now: DateTime<Utc>, zone: Zone) -> Option<Option<Filter>> {
+    match filter.map(|expr| Filter::parse(&expr, now, zone)).transpose() {
+        Ok(filter) => Some(filter),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            None
+        }
+    }
+}
+
+fn print_views(config: &Config, format: OutputFormat, zone: Zone) -> Result<(), io::Error> {
+    let records: Vec<Record> = config.views.iter().map(|(name, view)| view.record(name)).collect();
+    output::print(format, zone, &records, "No saved views.").map_err(std::io::Error::other)
+}
+
+// Parses an optional time argument; `None` once the error has been reported
+fn parse_time(input: Option<String>, now: DateTime<Utc>, zone: Zone) -> Option<Option<DateTime<Utc>>> {
+    match input.map(|input| time_parse::parse_when(&input, now, zone)).transpose() {
+        Ok(time) => Some(time),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            None
+        }
+    }
+}
+
+// Looks up a saved view; `None` once the error has been reported
+fn saved_view<'a>(config: &'a Config, name: &str) -> Option<&'a views::View> {
+    let view = config.views.get(name);
+    if view.is_none() {
+        eprintln!("Error: no view named '{}'", name);
+    }
+    view
+}
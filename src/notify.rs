@@ -0,0 +1,310 @@
+use crate::error::TaskError;
+use crate::shared::Task;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something the scheduler wants to tell the user about
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    /// "reminder", "digest" (reminders held back by quiet hours) or "overdue";
+    /// a digest carries the first of its tasks
+    pub event: String,
+    pub message: String,
+    pub at: DateTime<Utc>,
+    pub task: Task,
+}
+
+/// A destination for notifications. Implementations may block; the scheduler
+/// calls them off the async runtime.
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+    fn notify(&self, notification: &Notification) -> Result<(), TaskError>;
+}
+
+/// Notifier settings in the config file, e.g. `{ "type": "webhook", "url": "http://localhost:8080/hook" }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    Stdout,
+    LogFile { path: String },
+    Command { command: String },
+    Webhook { url: String },
+}
+
+pub fn build_notifiers(configs: &[NotifierConfig]) -> Result<Vec<Arc<dyn Notifier>>, TaskError> {
+    configs
+        .iter()
+        .map(|config| -> Result<Arc<dyn Notifier>, TaskError> {
+            Ok(match config {
+                NotifierConfig::Stdout => Arc::new(StdoutNotifier),
+                NotifierConfig::LogFile { path } => Arc::new(LogFileNotifier { path: path.clone() }),
+                NotifierConfig::Command { command } => Arc::new(CommandNotifier { command: command.clone() }),
+                NotifierConfig::Webhook { url } => Arc::new(WebhookNotifier::new(url)?),
+            })
+        })
+        .collect()
+}
+
+/// Deliver a notification to every sink, reporting failures without stopping at the first one
+pub async fn dispatch(notifiers: &[Arc<dyn Notifier>], notification: Notification) {
+    let notifiers = notifiers.to_vec();
+    let result = tokio::task::spawn_blocking(move || {
+        for notifier in &notifiers {
+            if let Err(err) = notifier.notify(&notification) {
+                eprintln!("Warning: {} notifier failed: {}", notifier.name(), err);
+            }
+        }
+    })
+    .await;
+    if let Err(err) = result {
+        eprintln!("Warning: notification delivery panicked: {}", err);
+    }
+}
+
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+        println!("{}", notification.message);
+        Ok(())
+    }
+}
+
+/// Appends one tab-separated line per notification
+pub struct LogFileNotifier {
+    pub path: String,
+}
+
+impl Notifier for LogFileNotifier {
+    fn name(&self) -> &str {
+        "log file"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}",
+            notification.at.to_rfc3339(),
+            notification.task.id,
+            notification.event,
+            notification.message
+        )?;
+        Ok(())
+    }
+}
+
+/// Runs a shell command with the task's fields in TODO_* environment variables
+pub struct CommandNotifier {
+    pub command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+        let task = &notification.task;
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(&self.command);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(&self.command);
+            command
+        };
+        let status = command
+            .env("TODO_EVENT", &notification.event)
+            .env("TODO_MESSAGE", &notification.message)
+            .env("TODO_AT", notification.at.to_rfc3339())
+            .env("TODO_TASK_ID", task.id.to_string())
+            .env("TODO_TASK_TITLE", &task.title)
+            .env("TODO_TASK_DETAILS", &task.details)
+            .env("TODO_TASK_START", task.start_time.to_rfc3339())
+            .env("TODO_TASK_END", task.end_time.to_rfc3339())
+            .env("TODO_TASK_DEADLINE", task.deadline.map(|d| d.to_rfc3339()).unwrap_or_default())
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(TaskError::Notify(format!("'{}' exited with {}", self.command, status)))
+        }
+    }
+}
+
+/// POSTs the notification as JSON to a plain http:// URL
+pub struct WebhookNotifier {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Result<Self, TaskError> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| TaskError::Config(format!("webhook URL '{}' must start with http://", url)))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| TaskError::Config(format!("invalid port in webhook URL '{}'", url)))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(TaskError::Config(format!("missing host in webhook URL '{}'", url)));
+        }
+        Ok(WebhookNotifier {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+        let body = serde_json::to_string(notification)?;
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+        stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            self.port,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let status = String::from_utf8_lossy(&response)
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| TaskError::Notify("webhook returned an invalid HTTP response".to_string()))?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(TaskError::Notify(format!("webhook returned HTTP {}", status)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn reminder() -> Notification {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        let mut task = Task::new("Standup".into(), "Daily sync".into(), start, start + chrono::Duration::minutes(15), false, None, None);
+        task.id = 7;
+        Notification {
+            event: "reminder".into(),
+            message: "Task 'Standup' starts in 5 minutes".into(),
+            at: start - chrono::Duration::minutes(5),
+            task,
+        }
+    }
+
+    // Accepts one request, answers with `status` and hands back the request line, headers and body
+    fn stand_in(status: &'static str) -> (String, thread::JoinHandle<(String, Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/todo", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                headers.push(line.trim_end().to_string());
+            }
+            let length: usize = headers
+                .iter()
+                .find_map(|header| header.strip_prefix("Content-Length: "))
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(reader.get_mut(), "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+            (request_line.trim_end().to_string(), headers, String::from_utf8(body).unwrap())
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn webhook_posts_the_notification_as_json() {
+        let (url, server) = stand_in("204 No Content");
+        let notifier = WebhookNotifier::new(&url).unwrap();
+        notifier.notify(&reminder()).unwrap();
+
+        let (request_line, headers, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /hooks/todo HTTP/1.1");
+        let host = url.trim_start_matches("http://").trim_end_matches("/hooks/todo");
+        assert!(headers.contains(&format!("Host: {}", host)));
+        assert!(headers.contains(&"Content-Type: application/json".to_string()));
+        assert!(headers.contains(&format!("Content-Length: {}", body.len())));
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["event"], "reminder");
+        assert_eq!(json["message"], "Task 'Standup' starts in 5 minutes");
+        assert_eq!(json["at"], "2030-01-01T08:55:00Z");
+        assert_eq!(json["task"]["id"], 7);
+        assert_eq!(json["task"]["title"], "Standup");
+    }
+
+    #[test]
+    fn webhook_reports_non_2xx_responses() {
+        let (url, server) = stand_in("500 Internal Server Error");
+        let err = WebhookNotifier::new(&url).unwrap().notify(&reminder()).unwrap_err();
+        server.join().unwrap();
+        assert!(matches!(err, TaskError::Notify(message) if message.contains("500")));
+    }
+
+    #[test]
+    fn webhook_urls_must_be_plain_http() {
+        assert!(WebhookNotifier::new("https://example.com/hook").is_err());
+        assert!(WebhookNotifier::new("http://:80/hook").is_err());
+        let notifier = WebhookNotifier::new("http://localhost").unwrap();
+        assert_eq!((notifier.host.as_str(), notifier.port, notifier.path.as_str()), ("localhost", 80, "/"));
+    }
+}
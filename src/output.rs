@@ -0,0 +1,417 @@
+use crate::config::Zone;
+use crate::error::TaskError;
+use crate::shared::{Priority, Task, TaskStatus};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Serialize, Serializer};
+use std::io::{self, IsTerminal, Write};
+
+// Matched text in search results is wrapped in these when it can't be coloured
+const MARK_OPEN: &str = "[";
+const MARK_CLOSE: &str = "]";
+
+pub const BOLD: &str = "\x1b[1m";
+pub const DIM: &str = "\x1b[2m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// How read commands print their results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Aligned columns, coloured when printing to a terminal
+    #[default]
+    Table,
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+    /// A YAML list
+    Yaml,
+}
+
+/// A single value in a record
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Time(DateTime<Utc>),
+    List(Vec<String>),
+    /// Text with some pieces flagged as matches
+    Marked(Vec<(String, bool)>),
+}
+
+impl Value {
+    pub fn time(time: Option<DateTime<Utc>>) -> Value {
+        time.map_or(Value::Null, Value::Time)
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Value::Int(_) | Value::Float(_))
+    }
+
+    // Text for tables, with times in the given zone, or with no zone plain text for CSV and YAML
+    fn to_text(&self, zone: Option<Zone>) -> String {
+        match (self, zone) {
+            (Value::Null, _) => String::new(),
+            (Value::Bool(value), _) => value.to_string(),
+            (Value::Int(value), _) => value.to_string(),
+            (Value::Float(value), _) => format!("{:.1}", value),
+            (Value::Text(text), _) => text.clone(),
+            (Value::Time(time), Some(zone)) => zone.to_local(*time).format("%Y-%m-%d %H:%M").to_string(),
+            (Value::Time(time), None) => time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            (Value::List(items), Some(_)) => items.iter().map(|item| format!("#{}", item)).collect::<Vec<_>>().join(" "),
+            (Value::List(items), None) => items.join(";"),
+            (Value::Marked(pieces), Some(_)) => pieces
+                .iter()
+                .map(|(text, marked)| if *marked { format!("{}{}{}", MARK_OPEN, text, MARK_CLOSE) } else { text.clone() })
+                .collect(),
+            (Value::Marked(pieces), None) => pieces.iter().map(|(text, _)| text.as_str()).collect(),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::Int(value) => serializer.serialize_i64(*value),
+            Value::Float(value) => serializer.serialize_f64(*value),
+            Value::Time(_) => serializer.serialize_str(&self.to_text(None)),
+            Value::List(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Text(_) | Value::Marked(_) => serializer.serialize_str(&self.to_text(None)),
+        }
+    }
+}
+
+struct Column {
+    name: &'static str,
+    value: Value,
+    /// Shown in tables; every column goes into the other formats
+    in_table: bool,
+}
+
+/// One row of output, with its columns in display order
+#[derive(Default)]
+pub struct Record {
+    columns: Vec<Column>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    pub fn field(mut self, name: &'static str, value: Value) -> Self {
+        self.columns.push(Column { name, value, in_table: true });
+        self
+    }
+
+    /// A column left out of tables to keep them narrow
+    pub fn detail(mut self, name: &'static str, value: Value) -> Self {
+        self.columns.push(Column { name, value, in_table: false });
+        self
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in &self.columns {
+            map.serialize_entry(column.name, &column.value)?;
+        }
+        map.end()
+    }
+}
+
+/// The columns every command listing tasks prints
+pub fn task_record(task: &Task) -> Record {
+    Record::new()
+        .field("id", Value::Int(task.id as i64))
+        .field("start", Value::Time(task.start_time))
+        .field("end", Value::Time(task.end_time))
+        .field("status", Value::Text(task.status.to_string()))
+        .field("priority", Value::Text(task.priority.to_string()))
+        .field("title", Value::Text(task.title.clone()))
+        .field("tags", Value::List(task.tags.clone()))
+        .detail("details", Value::Text(task.details.clone()))
+        .detail("notes", Value::Text(task.notes.clone()))
+        .detail("deadline", Value::time(task.deadline))
+        .detail("series", task.series_id.map_or(Value::Null, |id| Value::Int(id as i64)))
+        .detail("created_at", Value::time(task.created_at))
+        .detail("completed_at", Value::time(task.completed_at))
+}
+
+/// Prints `records` to stdout, with times in tables shown in `zone`; `empty` is printed instead of an empty table
+pub fn print(format: OutputFormat, zone: Zone, records: &[Record], empty: &str) -> Result<(), TaskError> {
+    let colour = use_colour();
+    let mut out = io::stdout().lock();
+    match format {
+        OutputFormat::Table if records.is_empty() => writeln!(out, "{}", empty)?,
+        OutputFormat::Table => write_table(&mut out, records, zone, colour)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut out, record)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => write_csv(&mut out, records)?,
+        OutputFormat::Yaml => write_yaml(&mut out, records)?,
+    }
+    Ok(())
+}
+
+/// Whether tables are coloured: only on a terminal, and not when `NO_COLOR` is set
+pub fn use_colour() -> bool {
+    io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn write_table(out: &mut impl Write, records: &[Record], zone: Zone, colour: bool) -> io::Result<()> {
+    let columns: Vec<&Column> = records[0].columns.iter().filter(|column| column.in_table).collect();
+    let cells: Vec<Vec<String>> = records
+        .iter()
+        .map(|record| record.columns.iter().filter(|column| column.in_table).map(|column| column.value.to_text(Some(zone))).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| cells.iter().map(|row| row[i].chars().count()).chain([column.name.len()]).max().unwrap_or(0))
+        .collect();
+
+    let last = columns.len() - 1;
+    for (i, column) in columns.iter().enumerate() {
+        let header = column.name.to_uppercase();
+        let padded = pad(&header, widths[i], column.value.is_number(), i == last);
+        write!(out, "{}{}", if i > 0 { "  " } else { "" }, paint(&padded, BOLD, colour))?;
+    }
+    writeln!(out)?;
+    for (record, row) in records.iter().zip(&cells) {
+        let shown = record.columns.iter().filter(|column| column.in_table);
+        for (i, (column, text)) in shown.zip(row).enumerate() {
+            if i > 0 {
+                write!(out, "  ")?;
+            }
+            let padding = widths[i] - text.chars().count();
+            match &column.value {
+                Value::Marked(pieces) if colour => {
+                    for (piece, marked) in pieces {
+                        if *marked {
+                            write!(out, "{}", paint(piece, &format!("{}{}", BOLD, YELLOW), true))?;
+                        } else {
+                            write!(out, "{}", piece)?;
+                        }
+                    }
+                    if i < last {
+                        write!(out, "{}", " ".repeat(padding))?;
+                    }
+                }
+                value => {
+                    let padded = pad(text, widths[i], value.is_number(), i == last);
+                    write!(out, "{}", paint(&padded, style(column.name, text), colour))?;
+                }
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// Numbers line up on the right; the last column isn't padded
+fn pad(text: &str, width: usize, right: bool, last: bool) -> String {
+    if right {
+        format!("{:>width$}", text, width = width)
+    } else if last {
+        text.to_string()
+    } else {
+        format!("{:<width$}", text, width = width)
+    }
+}
+
+pub fn paint(text: &str, style: &str, colour: bool) -> String {
+    if colour && !style.is_empty() {
+        format!("{}{}{}", style, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+// Colour for a cell, picked by what the column holds
+fn style(column: &str, text: &str) -> &'static str {
+    match column {
+        "status" => match clap::ValueEnum::from_str(text, true) {
+            Ok(TaskStatus::Done) => GREEN,
+            Ok(TaskStatus::Missed) => RED,
+            _ => "",
+        },
+        "priority" => match clap::ValueEnum::from_str(text, true) {
+            Ok(Priority::Urgent) => RED,
+            Ok(Priority::High) => YELLOW,
+            Ok(Priority::Low) => DIM,
+            _ => "",
+        },
+        "tags" => DIM,
+        "late" => RED,
+        _ => "",
+    }
+}
+
+fn write_csv(out: &mut impl Write, records: &[Record]) -> Result<(), TaskError> {
+    let mut writer = csv::Writer::from_writer(out);
+    if let Some(first) = records.first() {
+        writer.write_record(first.columns.iter().map(|column| column.name))?;
+    }
+    for record in records {
+        writer.write_record(record.columns.iter().map(|column| column.value.to_text(None)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_yaml(out: &mut impl Write, records: &[Record]) -> io::Result<()> {
+    if records.is_empty() {
+        return writeln!(out, "[]");
+    }
+    for record in records {
+        for (i, column) in record.columns.iter().enumerate() {
+            let prefix = if i == 0 { "- " } else { "  " };
+            match &column.value {
+                Value::List(items) if items.is_empty() => writeln!(out, "{}{}: []", prefix, column.name)?,
+                Value::List(items) => {
+                    writeln!(out, "{}{}:", prefix, column.name)?;
+                    for item in items {
+                        writeln!(out, "    - {}", yaml_scalar(item))?;
+                    }
+                }
+                Value::Null => writeln!(out, "{}{}: null", prefix, column.name)?,
+                Value::Text(_) | Value::Marked(_) => {
+                    writeln!(out, "{}{}: {}", prefix, column.name, yaml_scalar(&column.value.to_text(None)))?
+                }
+                value => writeln!(out, "{}{}: {}", prefix, column.name, value.to_text(None))?,
+            }
+        }
+    }
+    Ok(())
+}
+
+// Strings that YAML would read as something else, or can't hold plainly, are quoted
+fn yaml_scalar(text: &str) -> String {
+    let reserved = ["", "~", "null", "true", "false", "yes", "no", "on", "off"];
+    let needs_quotes = reserved.contains(&text.to_lowercase().as_str())
+        || text.parse::<f64>().is_ok()
+        || text.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        || text.ends_with(char::is_whitespace)
+        || text.contains(": ")
+        || text.contains(" #")
+        || text.contains(char::is_control);
+    if needs_quotes {
+        serde_json::to_string(text).unwrap_or_default()
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn records() -> Vec<Record> {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        vec![
+            Record::new()
+                .field("id", Value::Int(1))
+                .field("start", Value::Time(start))
+                .field("title", Value::Text("Standup, daily".into()))
+                .field("tags", Value::List(vec!["ops".into(), "team".into()]))
+                .detail("notes", Value::Text("say \"hi\"".into()))
+                .detail("deadline", Value::Null),
+            Record::new()
+                .field("id", Value::Int(12))
+                .field("start", Value::Time(start))
+                .field("title", Value::Marked(vec![("Plan ".into(), false), ("roadmap".into(), true)]))
+                .field("tags", Value::List(Vec::new()))
+                .detail("notes", Value::Text("yes".into()))
+                .detail("deadline", Value::Time(start)),
+        ]
+    }
+
+    fn render(write: impl Fn(&mut Vec<u8>, &[Record])) -> String {
+        let mut out = Vec::new();
+        write(&mut out, &records());
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_has_a_header_and_every_column() {
+        let csv = render(|out, records| write_csv(out, records).unwrap());
+        assert_eq!(
+            csv,
+            "id,start,title,tags,notes,deadline\n\
+             1,2030-01-01T09:00:00Z,\"Standup, daily\",ops;team,\"say \"\"hi\"\"\",\n\
+             12,2030-01-01T09:00:00Z,Plan roadmap,,yes,2030-01-01T09:00:00Z\n"
+        );
+    }
+
+    #[test]
+    fn yaml_is_a_list_of_maps() {
+        let yaml = render(|out, records| write_yaml(out, records).unwrap());
+        assert_eq!(
+            yaml,
+            "- id: 1\n  start: 2030-01-01T09:00:00Z\n  title: Standup, daily\n  tags:\n    - ops\n    - team\n  notes: say \"hi\"\n  deadline: null\n\
+             - id: 12\n  start: 2030-01-01T09:00:00Z\n  title: Plan roadmap\n  tags: []\n  notes: \"yes\"\n  deadline: 2030-01-01T09:00:00Z\n"
+        );
+
+        let mut empty = Vec::new();
+        write_yaml(&mut empty, &[]).unwrap();
+        assert_eq!(empty, b"[]\n");
+    }
+
+    #[test]
+    fn yaml_quotes_what_would_read_as_something_else() {
+        for text in ["", "null", "True", "12", "-1.5", "- item", "key: value", "a #comment", "[x]", " padded", "two\nlines"] {
+            assert!(yaml_scalar(text).starts_with('"'), "{:?} should be quoted", text);
+        }
+        for text in ["Standup", "release-2", "12 monkeys", "a:b", "ready?"] {
+            assert_eq!(yaml_scalar(text), text);
+        }
+        assert_eq!(yaml_scalar("two\nlines"), "\"two\\nlines\"");
+    }
+
+    #[test]
+    fn json_keeps_types_and_tables_leave_details_out() {
+        let json = serde_json::to_value(records()).unwrap();
+        assert_eq!(json[0]["id"], 1);
+        assert_eq!(json[0]["tags"], serde_json::json!(["ops", "team"]));
+        assert_eq!(json[0]["deadline"], serde_json::Value::Null);
+        assert_eq!(json[1]["title"], "Plan roadmap");
+
+        // times are shown in the configured zone, matches in brackets
+        let zone = Zone::Fixed(FixedOffset::east_opt(2 * 3600).unwrap());
+        let table = render(|out, records| write_table(out, records, zone, false).unwrap());
+        assert_eq!(
+            table,
+            "ID  START             TITLE           TAGS\n \
+             1  2030-01-01 11:00  Standup, daily  #ops #team\n\
+             12  2030-01-01 11:00  Plan [roadmap]  \n"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::shared::Task;
+use crate::time_parse::{humanize_duration, CompactDuration};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Follow-up alerts for tasks that are still pending once they are due
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Escalation {
+    /// How long after the due time to alert again, e.g. ["1h", "1d"]
+    pub after: Vec<CompactDuration>,
+    /// Raise the task's priority one level with each follow-up
+    pub bump_priority: bool,
+}
+
+impl Default for Escalation {
+    fn default() -> Self {
+        Escalation {
+            after: vec![CompactDuration(Duration::hours(1)), CompactDuration(Duration::days(1))],
+            bump_priority: false,
+        }
+    }
+}
+
+impl Escalation {
+    /// Alert times for an overdue task: when it falls due, then each follow-up. The position
+    /// in this list is the escalation level. Past occurrences of a recurring series only get
+    /// the first alert; the follow-ups go with the live occurrence, so a series left undone
+    /// doesn't escalate once per occurrence.
+    pub fn alert_times(&self, task: &Task) -> Vec<DateTime<Utc>> {
+        let due = task.due();
+        let past_occurrence = task.series_id.is_some() && !task.is_recurring;
+        let follow_ups = if past_occurrence { &[][..] } else { &self.after[..] };
+        std::iter::once(due)
+            .chain(follow_ups.iter().filter_map(|after| due.checked_add_signed(after.0)))
+            .collect()
+    }
+}
+
+/// Alert text, e.g. "Task 'Report' is overdue" or "Task 'Report' is overdue by 1 hour"
+pub fn overdue_message(task: &Task, at: DateTime<Utc>) -> String {
+    let late = at - task.due();
+    if late < Duration::minutes(1) {
+        format!("Task '{}' is overdue", task.title)
+    } else {
+        format!("Task '{}' is overdue by {}", task.title, humanize_duration(late))
+    }
+}
+
+/// Pending tasks past their due time, most overdue first
+pub fn overdue_tasks(tasks: &[Task], now: DateTime<Utc>) -> Vec<Task> {
+    let mut overdue: Vec<Task> = tasks.iter().filter(|task| task.is_overdue(now)).cloned().collect();
+    overdue.sort_by_key(|task| (task.due(), task.id));
+    overdue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn task(id: usize, start: DateTime<Utc>) -> Task {
+        let mut task = Task::new("Report".into(), String::new(), start, start + Duration::hours(1), false, None, None);
+        task.id = id;
+        task
+    }
+
+    #[test]
+    fn alerts_when_due_and_at_each_follow_up() {
+        let escalation = Escalation::default();
+        let mut report = task(1, at(1, 9));
+        assert_eq!(escalation.alert_times(&report), [at(1, 10), at(1, 11), at(2, 10)]);
+
+        // the deadline wins over the end time
+        report.deadline = Some(at(3, 17));
+        assert_eq!(escalation.alert_times(&report)[0], at(3, 17));
+        let none = Escalation { after: Vec::new(), bump_priority: false };
+        assert_eq!(none.alert_times(&report), [at(3, 17)]);
+    }
+
+    #[test]
+    fn only_the_live_occurrence_of_a_series_escalates() {
+        let escalation = Escalation::default();
+        let mut head = task(1, at(2, 9));
+        head.is_recurring = true;
+        head.recurrence = Some("FREQ=DAILY".parse().unwrap());
+        assert_eq!(escalation.alert_times(&head).len(), 3);
+
+        let mut past = task(2, at(1, 9));
+        past.series_id = Some(1);
+        assert_eq!(escalation.alert_times(&past), [at(1, 10)]);
+    }
+
+    #[test]
+    fn lists_overdue_tasks_most_overdue_first() {
+        let mut done = task(3, at(1, 6));
+        done.status = crate::shared::TaskStatus::Done;
+        let tasks = [task(1, at(1, 8)), task(2, at(1, 7)), done, task(4, at(1, 20))];
+        let ids: Vec<usize> = overdue_tasks(&tasks, at(1, 12)).iter().map(|task| task.id).collect();
+        assert_eq!(ids, [2, 1]);
+
+        assert_eq!(overdue_message(&tasks[0], at(1, 9)), "Task 'Report' is overdue");
+        assert_eq!(overdue_message(&tasks[0], at(1, 10)), "Task 'Report' is overdue by 1 hour");
+    }
+}
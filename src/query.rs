@@ -0,0 +1,157 @@
+use crate::filter::Filter;
+use crate::shared::{Task, TaskStatus};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Field a task list is ordered by; ties are broken by ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Start,
+    End,
+    /// Most important first
+    Priority,
+    Id,
+}
+
+/// Which tasks to list and in what order. Every filter that is set must match.
+#[derive(Clone, Default)]
+pub struct TaskQuery {
+    /// Only tasks that end after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only tasks that start before this time
+    pub to: Option<DateTime<Utc>>,
+    pub status: Option<TaskStatus>,
+    pub recurring: Option<bool>,
+    /// Tasks must carry every one of these tags
+    pub tags: Vec<String>,
+    /// Case-insensitive text to find in the title, details or notes
+    pub text: Option<String>,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    /// Parsed `--where` expression
+    pub filter: Option<Filter>,
+}
+
+impl TaskQuery {
+    pub fn matches(&self, task: &Task) -> bool {
+        if self.from.is_some_and(|from| task.end_time <= from) || self.to.is_some_and(|to| task.start_time >= to) {
+            return false;
+        }
+        if self.status.is_some_and(|status| task.status != status) {
+            return false;
+        }
+        if self.recurring.is_some_and(|recurring| task.series_id.is_some() != recurring) {
+            return false;
+        }
+        if !self.tags.iter().all(|tag| task.has_tag(tag)) {
+            return false;
+        }
+        if let Some(text) = &self.text {
+            let text = text.to_lowercase();
+            if ![&task.title, &task.details, &task.notes].iter().any(|field| field.to_lowercase().contains(&text)) {
+                return false;
+            }
+        }
+        self.filter.as_ref().is_none_or(|filter| filter.matches(task))
+    }
+
+    /// Every matching task, sorted, before `offset` and `limit` are applied
+    pub fn filter(&self, tasks: &[Task]) -> Vec<Task> {
+        let mut found: Vec<Task> = tasks.iter().filter(|task| self.matches(task)).cloned().collect();
+        match self.sort {
+            SortKey::Start => found.sort_by_key(|task| (task.start_time, task.id)),
+            SortKey::End => found.sort_by_key(|task| (task.end_time, task.id)),
+            SortKey::Priority => found.sort_by_key(|task| (std::cmp::Reverse(task.priority), task.start_time, task.id)),
+            SortKey::Id => found.sort_by_key(|task| task.id),
+        }
+        if self.reverse {
+            found.reverse();
+        }
+        found
+    }
+
+    /// The page of matching tasks selected by `offset` and `limit`
+    pub fn run(&self, tasks: &[Task]) -> Vec<Task> {
+        self.filter(tasks)
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Zone;
+    use crate::shared::Priority;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn task(id: usize, title: &str, day: u32, priority: Priority, tags: &[&str]) -> Task {
+        let mut task = Task::new(title.into(), String::new(), at(day, 9), at(day, 10), false, None, None);
+        task.id = id;
+        task.priority = priority;
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task
+    }
+
+    fn tasks() -> Vec<Task> {
+        let mut done = task(2, "Deploy fix", 1, Priority::Urgent, &["ops"]);
+        done.status = TaskStatus::Done;
+        let mut standup = task(4, "Standup", 3, Priority::Normal, &["team"]);
+        standup.series_id = Some(4);
+        standup.notes = "Ask about the deploy".into();
+        vec![
+            task(1, "Deploy release", 2, Priority::High, &["ops", "release"]),
+            done,
+            task(3, "Lunch", 2, Priority::Low, &[]),
+            standup,
+        ]
+    }
+
+    fn ids(query: &TaskQuery) -> Vec<usize> {
+        query.run(&tasks()).iter().map(|task| task.id).collect()
+    }
+
+    #[test]
+    fn every_filter_that_is_set_must_match() {
+        assert_eq!(ids(&TaskQuery::default()), [2, 1, 3, 4]);
+        assert_eq!(ids(&TaskQuery { status: Some(TaskStatus::Pending), tags: vec!["ops".into()], ..TaskQuery::default() }), [1]);
+        assert_eq!(ids(&TaskQuery { tags: vec!["ops".into(), "release".into()], ..TaskQuery::default() }), [1]);
+        assert_eq!(ids(&TaskQuery { recurring: Some(true), ..TaskQuery::default() }), [4]);
+        assert_eq!(ids(&TaskQuery { text: Some("DEPLOY".into()), recurring: Some(false), ..TaskQuery::default() }), [2, 1]);
+
+        let filter = Filter::parse("priority>=high", at(1, 0), Zone::Fixed(FixedOffset::east_opt(0).unwrap())).unwrap();
+        assert_eq!(ids(&TaskQuery { filter: Some(filter), status: Some(TaskStatus::Pending), ..TaskQuery::default() }), [1]);
+    }
+
+    #[test]
+    fn ranges_keep_tasks_that_overlap_them() {
+        // tasks run 09:00-10:00
+        assert_eq!(ids(&TaskQuery { from: Some(at(2, 9)), to: Some(at(3, 9)), ..TaskQuery::default() }), [1, 3]);
+        assert_eq!(ids(&TaskQuery { from: Some(at(2, 10)), ..TaskQuery::default() }), [4]);
+        assert_eq!(ids(&TaskQuery { to: Some(at(2, 9) + chrono::Duration::minutes(1)), ..TaskQuery::default() }), [2, 1, 3]);
+    }
+
+    #[test]
+    fn sorts_then_pages() {
+        let by = |sort, reverse| TaskQuery { sort, reverse, ..TaskQuery::default() };
+        assert_eq!(ids(&by(SortKey::Priority, false)), [2, 1, 4, 3]);
+        assert_eq!(ids(&by(SortKey::Id, true)), [4, 3, 2, 1]);
+        assert_eq!(ids(&by(SortKey::End, false)), [2, 1, 3, 4]);
+
+        let page = TaskQuery { sort: SortKey::Id, offset: 1, limit: Some(2), ..TaskQuery::default() };
+        assert_eq!(ids(&page), [2, 3]);
+        assert_eq!(page.filter(&tasks()).len(), 4);
+        assert!(ids(&TaskQuery { offset: 9, ..TaskQuery::default() }).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::config::Zone;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+// Back-to-back quiet periods are followed at most this far
+const MAX_CHAINED_PERIODS: usize = 14;
+
+/// A nightly (or daily) period without reminders, e.g. 22:00 to 07:00 on weekdays.
+/// A period whose end is before its start runs past midnight; `days` are the days it starts on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Days the period applies to; every day when empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// What happens to reminders that fall within quiet hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuietPolicy {
+    /// Send each one when the quiet period ends
+    #[default]
+    Defer,
+    /// Send one summary of all of them when the quiet period ends
+    Batch,
+}
+
+impl QuietHours {
+    // The period starting on `date`, if it applies on that day
+    fn period(&self, date: NaiveDate, zone: Zone) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.days.is_empty() && !self.days.contains(&date.weekday()) {
+            return None;
+        }
+        let end_date = if self.end <= self.start { date + Duration::days(1) } else { date };
+        Some((zone.to_utc(date.and_time(self.start)), zone.to_utc(end_date.and_time(self.end))))
+    }
+}
+
+/// The end of the quiet period `at` falls in, or `None` if it isn't quiet then
+pub fn quiet_until(rules: &[QuietHours], zone: Zone, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut until = None;
+    let mut current = at;
+    for _ in 0..MAX_CHAINED_PERIODS {
+        let date = zone.to_local(current).date();
+        let end = rules
+            .iter()
+            .flat_map(|rule| [rule.period(date - Duration::days(1), zone), rule.period(date, zone)])
+            .flatten()
+            .filter(|(start, end)| *start <= current && current < *end)
+            .map(|(_, end)| end)
+            .max();
+        match end {
+            Some(end) => {
+                until = Some(end);
+                current = end;
+            }
+            None => break,
+        }
+    }
+    until
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    // 2030-01-04 is a Friday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn quiet(days: &[Weekday], start: &str, end: &str) -> QuietHours {
+        QuietHours { days: days.to_vec(), start: start.parse().unwrap(), end: end.parse().unwrap() }
+    }
+
+    fn utc() -> Zone {
+        Zone::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+
+    #[test]
+    fn periods_can_run_past_midnight() {
+        let rules = [quiet(&[], "22:00", "07:00")];
+        assert_eq!(quiet_until(&rules, utc(), at(2, 23, 0)), Some(at(3, 7, 0)));
+        assert_eq!(quiet_until(&rules, utc(), at(3, 6, 59)), Some(at(3, 7, 0)));
+        assert_eq!(quiet_until(&rules, utc(), at(3, 7, 0)), None);
+        assert_eq!(quiet_until(&rules, utc(), at(3, 21, 59)), None);
+    }
+
+    #[test]
+    fn days_are_the_days_a_period_starts_on() {
+        let rules = [quiet(&[Weekday::Fri], "22:00", "09:00")];
+        // Friday night runs into Saturday morning
+        assert_eq!(quiet_until(&rules, utc(), at(5, 8, 0)), Some(at(5, 9, 0)));
+        assert_eq!(quiet_until(&rules, utc(), at(3, 23, 0)), None);
+    }
+
+    #[test]
+    fn back_to_back_periods_are_followed() {
+        let rules = [quiet(&[], "22:00", "07:00"), quiet(&[], "07:00", "08:30")];
+        assert_eq!(quiet_until(&rules, utc(), at(2, 23, 0)), Some(at(3, 8, 30)));
+    }
+
+    #[test]
+    fn periods_are_read_in_the_configured_zone() {
+        let zone = Zone::Fixed(FixedOffset::east_opt(2 * 3600).unwrap());
+        let rules = [quiet(&[], "22:00", "07:00")];
+        assert_eq!(quiet_until(&rules, zone, at(2, 20, 30)), Some(at(3, 5, 0)));
+        assert_eq!(quiet_until(&rules, zone, at(3, 5, 0)), None);
+    }
+}
@@ -15,10 +15,14 @@ pub struct ReadWrite {
 impl ReadWrite {
     pub async fn save_to_file(&self, folder_path: &str) -> Result<(), TaskError> {
         let tasks = self.tasks.clone();
-        let mut file = fs::File::create(folder_path).await?;
-        let tasks: Vec<_> = tasks.iter().map(|entry| (entry.key().clone(), entry.value().clone())).collect();
+        let tasks: Vec<_> = tasks.iter().map(|entry| (*entry.key(), entry.value().clone())).collect();
         let contents = serde_json::to_string(&tasks)?;
+        // Written next to the store and renamed over it, so readers never see half a file
+        let temp_path = format!("{}.tmp", folder_path);
+        let mut file = fs::File::create(&temp_path).await?;
         file.write_all(contents.as_bytes()).await?;
+        file.sync_all().await?;
+        fs::rename(&temp_path, folder_path).await?;
         Ok(())
     }
 
@@ -0,0 +1,681 @@
+use crate::config::Zone;
+use crate::error::TaskError;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Upper bound on how many periods we walk before giving up on a rule that
+// never matches (e.g. BYMONTHDAY=31 combined with BYDAY=2MO)
+const MAX_PERIODS: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A BYDAY entry such as `TU`, `2TU` or `-1FR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i8>,
+    pub weekday: Weekday,
+}
+
+/// Subset of an RFC 5545 RRULE: FREQ, INTERVAL, BYDAY, BYMONTHDAY, BYSETPOS, UNTIL and COUNT.
+/// Stored and displayed in its RRULE text form, e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<WeekdayNum>,
+    pub by_month_day: Vec<i8>,
+    pub by_set_pos: Vec<i32>,
+    /// Last instant an occurrence may start at (inclusive)
+    pub until: Option<DateTime<Utc>>,
+    /// Total number of occurrences in the series, including the first one
+    pub count: Option<u32>,
+}
+
+impl Recurrence {
+    pub fn new(freq: Frequency) -> Self {
+        Recurrence {
+            freq,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_set_pos: Vec::new(),
+            until: None,
+            count: None,
+        }
+    }
+
+    /// Equivalent of the legacy `frequency_minutes` setting
+    pub fn every_minutes(minutes: i64) -> Self {
+        let mut rule = Recurrence::new(Frequency::Minutely);
+        rule.interval = minutes.max(1) as u32;
+        rule
+    }
+
+    /// First occurrence of the series anchored at `dtstart` that falls strictly after `after`.
+    /// Days and times of day are taken in `zone`, so BYDAY and BYMONTHDAY match local dates
+    /// and occurrences keep the wall-clock time of `dtstart` across DST changes.
+    /// Returns `None` once the series runs past UNTIL. COUNT is enforced by the caller,
+    /// which knows how many occurrences have already been generated.
+    pub fn next_after(&self, dtstart: DateTime<Utc>, after: DateTime<Utc>, zone: Zone) -> Option<DateTime<Utc>> {
+        let first_period = (self.periods_between(zone.to_local(dtstart), zone.to_local(after)) - 1).max(0);
+
+        for period in first_period..first_period + MAX_PERIODS {
+            let mut candidates = self.expand_period(dtstart, period, zone)?;
+            candidates.sort();
+            candidates.dedup();
+            let candidates = self.apply_set_pos(candidates);
+
+            if let Some(next) = candidates.into_iter().find(|c| *c > after && *c >= dtstart) {
+                return match self.until {
+                    Some(until) if next > until => None,
+                    _ => Some(next),
+                };
+            }
+        }
+        None
+    }
+
+    /// Whether an occurrence with the given zero-based index is still within COUNT
+    pub fn allows_index(&self, index: u32) -> bool {
+        self.count.is_none_or(|count| index < count)
+    }
+
+    // Rough number of whole intervals between the anchor and `after`, used to skip
+    // straight to the right period instead of walking from the start of the series
+    fn periods_between(&self, dtstart: NaiveDateTime, after: NaiveDateTime) -> i64 {
+        if after <= dtstart {
+            return 0;
+        }
+        let span = after - dtstart;
+        let units = match self.freq {
+            Frequency::Minutely => span.num_minutes(),
+            Frequency::Hourly => span.num_hours(),
+            Frequency::Daily => span.num_days(),
+            Frequency::Weekly => span.num_weeks(),
+            Frequency::Monthly => {
+                (after.year() as i64 - dtstart.year() as i64) * 12 + after.month() as i64
+                    - dtstart.month() as i64
+            }
+            Frequency::Yearly => after.year() as i64 - dtstart.year() as i64,
+        };
+        units / self.interval as i64
+    }
+
+    // Minutes and hours are counted in elapsed time, longer periods in local calendar days
+    fn expand_period(&self, dtstart: DateTime<Utc>, period: i64, zone: Zone) -> Option<Vec<DateTime<Utc>>> {
+        let step = period.checked_mul(self.interval as i64)?;
+        let local = zone.to_local(dtstart);
+        let time = local.time();
+        let at = |date: NaiveDate| zone.to_utc(date.and_time(time));
+
+        let candidates = match self.freq {
+            Frequency::Minutely | Frequency::Hourly => {
+                let offset = match self.freq {
+                    Frequency::Minutely => Duration::try_minutes(step)?,
+                    _ => Duration::try_hours(step)?,
+                };
+                let candidate = dtstart.checked_add_signed(offset)?;
+                if self.matches_day_filters(zone.to_local(candidate).date()) {
+                    vec![candidate]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Daily => {
+                let date = local.date().checked_add_signed(Duration::try_days(step)?)?;
+                if self.matches_day_filters(date) {
+                    vec![at(date)]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let anchor = local.date();
+                let week_start = anchor
+                    - Duration::days(anchor.weekday().num_days_from_monday() as i64)
+                    + Duration::try_weeks(step)?;
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![anchor.weekday()]
+                } else {
+                    self.by_day.iter().map(|d| d.weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .map(|wd| week_start + Duration::days(wd.num_days_from_monday() as i64))
+                    .filter(|date| self.matches_month_day(*date))
+                    .map(at)
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let months = local.year() as i64 * 12 + local.month0() as i64 + step;
+                let year = i32::try_from(months.div_euclid(12)).ok()?;
+                let month = months.rem_euclid(12) as u32 + 1;
+                self.month_dates(year, month, local.day())
+                    .into_iter()
+                    .map(at)
+                    .collect()
+            }
+            Frequency::Yearly => {
+                let year = local.year().checked_add(i32::try_from(step).ok()?)?;
+                self.year_dates(year, local.date())
+                    .into_iter()
+                    .map(at)
+                    .collect()
+            }
+        };
+        Some(candidates)
+    }
+
+    // BYDAY/BYMONTHDAY act as plain filters for sub-weekly frequencies
+    fn matches_day_filters(&self, date: NaiveDate) -> bool {
+        let weekday_ok = self.by_day.is_empty() || self.by_day.iter().any(|d| d.weekday == date.weekday());
+        weekday_ok && self.matches_month_day(date)
+    }
+
+    fn matches_month_day(&self, date: NaiveDate) -> bool {
+        if self.by_month_day.is_empty() {
+            return true;
+        }
+        let len = days_in_month(date.year(), date.month());
+        self.by_month_day
+            .iter()
+            .any(|md| resolve_month_day(*md, len) == Some(date.day()))
+    }
+
+    fn month_dates(&self, year: i32, month: u32, anchor_day: u32) -> Vec<NaiveDate> {
+        let len = days_in_month(year, month);
+        let days: Vec<u32> = if !self.by_month_day.is_empty() {
+            self.by_month_day
+                .iter()
+                .filter_map(|md| resolve_month_day(*md, len))
+                .collect()
+        } else if !self.by_day.is_empty() {
+            (1..=len).collect()
+        } else if anchor_day <= len {
+            vec![anchor_day]
+        } else {
+            Vec::new()
+        };
+
+        days.into_iter()
+            .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+            .filter(|date| {
+                self.by_day.is_empty()
+                    || self.by_day.iter().any(|d| {
+                        d.weekday == date.weekday()
+                            && d.ordinal.is_none_or(|n| nth_in_span(date.day(), len, n))
+                    })
+            })
+            .collect()
+    }
+
+    fn year_dates(&self, year: i32, anchor: NaiveDate) -> Vec<NaiveDate> {
+        let dates: Vec<NaiveDate> = if !self.by_month_day.is_empty() {
+            (1..=12)
+                .flat_map(|month| {
+                    let len = days_in_month(year, month);
+                    self.by_month_day
+                        .iter()
+                        .filter_map(move |md| resolve_month_day(*md, len))
+                        .filter_map(move |day| NaiveDate::from_ymd_opt(year, month, day))
+                })
+                .collect()
+        } else if !self.by_day.is_empty() {
+            NaiveDate::from_ymd_opt(year, 1, 1)
+                .map(|first| first.iter_days().take_while(|d| d.year() == year).collect())
+                .unwrap_or_default()
+        } else {
+            NaiveDate::from_ymd_opt(year, anchor.month(), anchor.day())
+                .into_iter()
+                .collect()
+        };
+
+        let year_len = if NaiveDate::from_ymd_opt(year, 2, 29).is_some() { 366 } else { 365 };
+        dates
+            .into_iter()
+            .filter(|date| {
+                self.by_day.is_empty()
+                    || self.by_day.iter().any(|d| {
+                        d.weekday == date.weekday()
+                            && d.ordinal.is_none_or(|n| nth_in_span(date.ordinal(), year_len, n))
+                    })
+            })
+            .collect()
+    }
+
+    fn apply_set_pos(&self, candidates: Vec<DateTime<Utc>>) -> Vec<DateTime<Utc>> {
+        if self.by_set_pos.is_empty() {
+            return candidates;
+        }
+        let len = candidates.len() as i32;
+        let mut selected: Vec<DateTime<Utc>> = self
+            .by_set_pos
+            .iter()
+            .filter_map(|pos| {
+                let index = if *pos > 0 { pos - 1 } else { len + pos };
+                usize::try_from(index).ok().and_then(|i| candidates.get(i).copied())
+            })
+            .collect();
+        selected.sort();
+        selected.dedup();
+        selected
+    }
+}
+
+// Whether `day` (1-based) is the nth occurrence of its weekday within a span of `len` days
+fn nth_in_span(day: u32, len: u32, n: i8) -> bool {
+    if n > 0 {
+        (day as i32 - 1) / 7 + 1 == n as i32
+    } else {
+        (len as i32 - day as i32) / 7 + 1 == -(n as i32)
+    }
+}
+
+fn resolve_month_day(month_day: i8, len: u32) -> Option<u32> {
+    let day = if month_day > 0 {
+        month_day as i32
+    } else {
+        len as i32 + 1 + month_day as i32
+    };
+    (1..=len as i32).contains(&day).then_some(day as u32)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(31)
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+// UNTIL accepts the RFC 5545 forms (20251231T170000Z, 20251231) as well as RFC 3339.
+// A bare date covers the whole day.
+fn parse_until(value: &str) -> Result<DateTime<Utc>, TaskError> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S") {
+        return Ok(Utc.from_utc_datetime(&dt));
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|dt| Utc.from_utc_datetime(&dt))
+        .ok_or_else(|| invalid(format!("invalid UNTIL value '{}'", value)))
+}
+
+fn invalid(msg: impl Into<String>) -> TaskError {
+    TaskError::InvalidRecurrence(msg.into())
+}
+
+impl FromStr for Frequency {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "MINUTELY" => Ok(Frequency::Minutely),
+            "HOURLY" => Ok(Frequency::Hourly),
+            "DAILY" => Ok(Frequency::Daily),
+            "WEEKLY" => Ok(Frequency::Weekly),
+            "MONTHLY" => Ok(Frequency::Monthly),
+            "YEARLY" => Ok(Frequency::Yearly),
+            other => Err(invalid(format!("unsupported FREQ '{}'", other))),
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Frequency::Minutely => "MINUTELY",
+            Frequency::Hourly => "HOURLY",
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for WeekdayNum {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        if s.len() < 2 {
+            return Err(invalid(format!("invalid BYDAY value '{}'", s)));
+        }
+        let (ordinal, code) = s.split_at(s.len() - 2);
+        let weekday = match code {
+            "MO" => Weekday::Mon,
+            "TU" => Weekday::Tue,
+            "WE" => Weekday::Wed,
+            "TH" => Weekday::Thu,
+            "FR" => Weekday::Fri,
+            "SA" => Weekday::Sat,
+            "SU" => Weekday::Sun,
+            _ => return Err(invalid(format!("invalid BYDAY value '{}'", s))),
+        };
+        let ordinal = if ordinal.is_empty() {
+            None
+        } else {
+            let n: i8 = ordinal
+                .parse()
+                .map_err(|_| invalid(format!("invalid BYDAY value '{}'", s)))?;
+            if n == 0 || !(-53..=53).contains(&n) {
+                return Err(invalid(format!("BYDAY ordinal out of range in '{}'", s)));
+            }
+            Some(n)
+        };
+        Ok(WeekdayNum { ordinal, weekday })
+    }
+}
+
+impl fmt::Display for WeekdayNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(n) = self.ordinal {
+            write!(f, "{}", n)?;
+        }
+        f.write_str(weekday_code(self.weekday))
+    }
+}
+
+fn parse_list<T>(key: &str, value: &str, range: std::ops::RangeInclusive<T>) -> Result<Vec<T>, TaskError>
+where
+    T: FromStr + PartialOrd + Default + Copy,
+{
+    value
+        .split(',')
+        .map(|item| {
+            let n: T = item
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("invalid {} value '{}'", key, item)))?;
+            if n == T::default() || !range.contains(&n) {
+                return Err(invalid(format!("{} value '{}' out of range", key, item)));
+            }
+            Ok(n)
+        })
+        .collect()
+}
+
+impl FromStr for Recurrence {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let body = s
+            .strip_prefix("RRULE:")
+            .or_else(|| s.strip_prefix("rrule:"))
+            .unwrap_or(s);
+
+        let mut freq = None;
+        let mut rule = Recurrence::new(Frequency::Daily);
+
+        for part in body.split(';').filter(|p| !p.trim().is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected KEY=VALUE, got '{}'", part)))?;
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => freq = Some(value.parse()?),
+                "INTERVAL" => {
+                    rule.interval = value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 1)
+                        .ok_or_else(|| invalid(format!("INTERVAL must be a positive integer, got '{}'", value)))?;
+                }
+                "BYDAY" => {
+                    rule.by_day = value.split(',').map(str::parse).collect::<Result<_, _>>()?;
+                }
+                "BYMONTHDAY" => rule.by_month_day = parse_list("BYMONTHDAY", value, -31..=31)?,
+                "BYSETPOS" => rule.by_set_pos = parse_list("BYSETPOS", value, -366..=366)?,
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|n| *n >= 1)
+                            .ok_or_else(|| invalid(format!("COUNT must be a positive integer, got '{}'", value)))?,
+                    );
+                }
+                other => return Err(invalid(format!("unsupported rule part '{}'", other))),
+            }
+        }
+
+        rule.freq = freq.ok_or_else(|| invalid("FREQ is required"))?;
+        if rule.until.is_some() && rule.count.is_some() {
+            return Err(invalid("UNTIL and COUNT cannot both be set"));
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self.by_day.iter().map(|d| d.to_string()).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|d| d.to_string()).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if !self.by_set_pos.is_empty() {
+            let positions: Vec<String> = self.by_set_pos.iter().map(|p| p.to_string()).collect();
+            write!(f, ";BYSETPOS={}", positions.join(","))?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = TaskError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(rule: Recurrence) -> Self {
+        rule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 9, 0, 0).unwrap()
+    }
+
+    fn utc() -> Zone {
+        Zone::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+
+    // The first `n` occurrences after the one at `dtstart`, expanded in UTC
+    fn expand(rule: &str, dtstart: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+        expand_in(utc(), rule, dtstart, n)
+    }
+
+    fn expand_in(zone: Zone, rule: &str, dtstart: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+        let rule: Recurrence = rule.parse().unwrap();
+        let mut out = Vec::new();
+        let mut after = dtstart;
+        while out.len() < n {
+            let Some(next) = rule.next_after(dtstart, after, zone) else { break };
+            out.push(next);
+            after = next;
+        }
+        out
+    }
+
+    #[test]
+    fn parses_and_prints_rules() {
+        let rule: Recurrence = "RRULE:freq=monthly;interval=2;byday=2MO,-1FR;bymonthday=1,-1;bysetpos=-1".parse().unwrap();
+        assert_eq!(rule.freq, Frequency::Monthly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.by_day, [
+            WeekdayNum { ordinal: Some(2), weekday: Weekday::Mon },
+            WeekdayNum { ordinal: Some(-1), weekday: Weekday::Fri },
+        ]);
+        assert_eq!(rule.by_month_day, [1, -1]);
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;INTERVAL=2;BYDAY=2MO,-1FR;BYMONTHDAY=1,-1;BYSETPOS=-1");
+        assert_eq!("FREQ=DAILY".parse::<Recurrence>().unwrap(), Recurrence::new(Frequency::Daily));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "",
+            "INTERVAL=2",
+            "FREQ=FORTNIGHTLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;COUNT=3;UNTIL=20250101",
+            "FREQ=DAILY;BYHOUR=9",
+            "FREQ=DAILY;INTERVAL",
+        ] {
+            assert!(rule.parse::<Recurrence>().is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn sub_weekly_frequencies_step_and_filter() {
+        assert_eq!(expand("FREQ=DAILY;INTERVAL=3", at(2025, 1, 1), 2), [at(2025, 1, 4), at(2025, 1, 7)]);
+        // 2025-01-06 is a Monday
+        assert_eq!(expand("FREQ=DAILY;BYDAY=MO,FR", at(2025, 1, 6), 3), [at(2025, 1, 10), at(2025, 1, 13), at(2025, 1, 17)]);
+        let start = at(2025, 1, 1);
+        assert_eq!(expand("FREQ=HOURLY;INTERVAL=2", start, 1), [start + Duration::hours(2)]);
+        assert_eq!(Recurrence::every_minutes(90).next_after(start, start, utc()), Some(start + Duration::minutes(90)));
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        assert_eq!(expand("FREQ=WEEKLY;BYDAY=MO,WE,FR", at(2025, 1, 6), 3), [at(2025, 1, 8), at(2025, 1, 10), at(2025, 1, 13)]);
+        // every second Tuesday
+        assert_eq!(expand("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU", at(2025, 1, 7), 2), [at(2025, 1, 21), at(2025, 2, 4)]);
+    }
+
+    #[test]
+    fn monthly_by_day_and_month_day() {
+        // second Monday
+        assert_eq!(expand("FREQ=MONTHLY;BYDAY=2MO", at(2025, 1, 13), 2), [at(2025, 2, 10), at(2025, 3, 10)]);
+        // last weekday of the month
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1", at(2025, 1, 31), 3),
+            [at(2025, 2, 28), at(2025, 3, 31), at(2025, 4, 30)]
+        );
+        assert_eq!(expand("FREQ=MONTHLY;BYMONTHDAY=-1", at(2025, 1, 31), 3), [at(2025, 2, 28), at(2025, 3, 31), at(2025, 4, 30)]);
+        // months without a 31st are skipped
+        assert_eq!(expand("FREQ=MONTHLY;BYMONTHDAY=31", at(2025, 1, 31), 2), [at(2025, 3, 31), at(2025, 5, 31)]);
+        assert_eq!(expand("FREQ=MONTHLY", at(2025, 1, 31), 2), [at(2025, 3, 31), at(2025, 5, 31)]);
+    }
+
+    #[test]
+    fn yearly() {
+        assert_eq!(expand("FREQ=YEARLY", at(2024, 2, 29), 1), [at(2028, 2, 29)]);
+        // last Friday of the year
+        assert_eq!(expand("FREQ=YEARLY;BYDAY=-1FR", at(2024, 12, 27), 1), [at(2025, 12, 26)]);
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        assert_eq!(expand("FREQ=DAILY;UNTIL=20250103T090000Z", at(2025, 1, 1), 5), [at(2025, 1, 2), at(2025, 1, 3)]);
+        // a bare date covers the whole day
+        assert_eq!(expand("FREQ=DAILY;UNTIL=20250102", at(2025, 1, 1), 5), [at(2025, 1, 2)]);
+        assert_eq!(expand("FREQ=DAILY;UNTIL=2025-01-02T09:00:00Z", at(2025, 1, 1), 5), [at(2025, 1, 2)]);
+        let rule: Recurrence = "FREQ=DAILY;UNTIL=20250102".parse().unwrap();
+        assert_eq!(rule.to_string(), "FREQ=DAILY;UNTIL=20250102T235959Z");
+    }
+
+    #[test]
+    fn count_limits_occurrence_indexes() {
+        let rule: Recurrence = "FREQ=DAILY;COUNT=3".parse().unwrap();
+        assert!(rule.allows_index(2));
+        assert!(!rule.allows_index(3));
+        assert!(Recurrence::new(Frequency::Daily).allows_index(u32::MAX));
+    }
+
+    #[test]
+    fn rules_that_never_match_end() {
+        assert!(expand("FREQ=MONTHLY;BYMONTHDAY=31;BYDAY=2MO", at(2025, 1, 1), 1).is_empty());
+    }
+
+    #[test]
+    fn days_are_matched_in_the_configured_zone() {
+        let brisbane = Zone::Fixed(FixedOffset::east_opt(10 * 3600).unwrap());
+        // Monday 2030-01-07 08:00 in UTC+10 is still Sunday in UTC
+        let monday = Utc.with_ymd_and_hms(2030, 1, 6, 22, 0, 0).unwrap();
+        assert_eq!(
+            expand_in(brisbane, "FREQ=WEEKLY;BYDAY=MO", monday, 2),
+            [monday + Duration::weeks(1), monday + Duration::weeks(2)]
+        );
+        assert_eq!(
+            expand_in(brisbane, "FREQ=MONTHLY;BYMONTHDAY=-1", monday, 2),
+            [Utc.with_ymd_and_hms(2030, 1, 30, 22, 0, 0).unwrap(), Utc.with_ymd_and_hms(2030, 2, 27, 22, 0, 0).unwrap()]
+        );
+        assert_eq!(expand_in(brisbane, "FREQ=DAILY;BYDAY=SA", monday, 1), [Utc.with_ymd_and_hms(2030, 1, 11, 22, 0, 0).unwrap()]);
+    }
+
+    #[test]
+    fn occurrences_keep_their_wall_clock_time_across_dst() {
+        // Europe/Berlin moves from UTC+1 to UTC+2 on 2030-03-31
+        std::env::set_var("TZ", "Europe/Berlin");
+        let start = Utc.with_ymd_and_hms(2030, 3, 29, 8, 0, 0).unwrap();
+        assert_eq!(
+            expand_in(Zone::Local, "FREQ=DAILY", start, 3),
+            [
+                Utc.with_ymd_and_hms(2030, 3, 30, 8, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2030, 3, 31, 7, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2030, 4, 1, 7, 0, 0).unwrap(),
+            ]
+        );
+        // elapsed-time rules are not shifted
+        assert_eq!(expand_in(Zone::Local, "FREQ=HOURLY;INTERVAL=24", start, 2)[1], start + Duration::hours(48));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::error::TaskError;
+use crate::shared::Task;
+use crate::time_parse::{format_duration, humanize_duration, parse_duration};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Task time a relative reminder is measured from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    Start,
+    End,
+    Deadline,
+}
+
+/// When to remind about a task: relative to its start, end or deadline
+/// (`start-5m`, `end-2m`, `deadline-1d`, `start+10m`) or at an absolute time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Reminder {
+    Relative { anchor: Anchor, offset: Duration },
+    At(DateTime<Utc>),
+}
+
+impl Reminder {
+    pub fn before(anchor: Anchor, offset: Duration) -> Self {
+        Reminder::Relative { anchor, offset: -offset }
+    }
+
+    /// Reminders used when neither the task nor the config lists any
+    pub fn defaults() -> Vec<Reminder> {
+        vec![
+            Reminder::before(Anchor::Start, Duration::minutes(5)),
+            Reminder::before(Anchor::End, Duration::minutes(2)),
+        ]
+    }
+
+    /// When this reminder fires for `task`; `None` for a deadline reminder on a task without a deadline
+    pub fn fire_time(&self, task: &Task) -> Option<DateTime<Utc>> {
+        match self {
+            Reminder::At(at) => Some(*at),
+            Reminder::Relative { anchor, offset } => {
+                let base = match anchor {
+                    Anchor::Start => task.start_time,
+                    Anchor::End => task.end_time,
+                    Anchor::Deadline => task.deadline?,
+                };
+                base.checked_add_signed(*offset)
+            }
+        }
+    }
+
+    /// Reminder text, e.g. "Reminder: 'Standup' starts in 5 minutes!"
+    pub fn message(&self, task: &Task) -> String {
+        let Reminder::Relative { anchor, offset } = self else {
+            return format!("Reminder: '{}'", task.title);
+        };
+        let (soon, now, past) = match anchor {
+            Anchor::Start => ("starts in", "starts now", "started"),
+            Anchor::End => ("ends in", "ends now", "ended"),
+            Anchor::Deadline => ("is due in", "is due now", "was due"),
+        };
+        let when = if *offset < Duration::zero() {
+            format!("{} {}", soon, humanize_duration(*offset))
+        } else if *offset == Duration::zero() {
+            now.to_string()
+        } else {
+            format!("{} {} ago", past, humanize_duration(*offset))
+        };
+        format!("Reminder: '{}' {}!", task.title, when)
+    }
+}
+
+impl FromStr for Reminder {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(at) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Reminder::At(at.with_timezone(&Utc)));
+        }
+
+        let lower = s.to_ascii_lowercase();
+        let split = lower.find(['+', '-']).unwrap_or(lower.len());
+        let (name, rest) = lower.split_at(split);
+        let anchor = match name {
+            "start" => Anchor::Start,
+            "end" => Anchor::End,
+            "deadline" => Anchor::Deadline,
+            _ => {
+                return Err(TaskError::InvalidTime(format!(
+                    "invalid reminder '{}', expected e.g. start-5m, end-2m, deadline-1d or an ISO 8601 time",
+                    s
+                )))
+            }
+        };
+        let offset = match rest.chars().next() {
+            None => Duration::zero(),
+            Some('-') => -parse_duration(&rest[1..])?,
+            Some(_) => parse_duration(&rest[1..])?,
+        };
+        Ok(Reminder::Relative { anchor, offset })
+    }
+}
+
+impl fmt::Display for Reminder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reminder::At(at) => write!(f, "{}", at.to_rfc3339()),
+            Reminder::Relative { anchor, offset } => {
+                let name = match anchor {
+                    Anchor::Start => "start",
+                    Anchor::End => "end",
+                    Anchor::Deadline => "deadline",
+                };
+                if *offset == Duration::zero() {
+                    f.write_str(name)
+                } else if *offset < Duration::zero() {
+                    write!(f, "{}-{}", name, format_duration(-*offset))
+                } else {
+                    write!(f, "{}+{}", name, format_duration(*offset))
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Reminder {
+    type Error = TaskError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Reminder> for String {
+    fn from(reminder: Reminder) -> Self {
+        reminder.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    fn report() -> Task {
+        Task::new("Report".into(), String::new(), at(9, 0), at(12, 0), false, None, None)
+    }
+
+    #[test]
+    fn parses_and_prints_round_trip() {
+        for text in ["start-5m", "end-2m", "deadline-1d", "start+10m", "start", "end-1h30m", "2030-01-01T08:00:00+00:00"] {
+            let reminder: Reminder = text.parse().unwrap();
+            assert_eq!(reminder.to_string(), text);
+            assert_eq!(reminder.to_string().parse::<Reminder>().unwrap(), reminder);
+        }
+        assert_eq!(" Start-90 ".parse::<Reminder>().unwrap().to_string(), "start-1h30m");
+        assert_eq!(
+            "2030-01-01T10:00:00+02:00".parse::<Reminder>().unwrap(),
+            Reminder::At(at(8, 0))
+        );
+        assert_eq!(serde_json::to_string(&Reminder::before(Anchor::End, Duration::minutes(2))).unwrap(), "\"end-2m\"");
+        for text in ["begin-5m", "start-soon", "start-", ""] {
+            assert!(text.parse::<Reminder>().is_err(), "{:?} should not parse", text);
+        }
+    }
+
+    #[test]
+    fn fire_times_follow_their_anchor() {
+        let mut task = report();
+        let fire = |text: &str, task: &Task| text.parse::<Reminder>().unwrap().fire_time(task);
+        assert_eq!(fire("start-15m", &task), Some(at(8, 45)));
+        assert_eq!(fire("start+10m", &task), Some(at(9, 10)));
+        assert_eq!(fire("end-2m", &task), Some(at(11, 58)));
+        assert_eq!(fire("2030-01-01T07:00:00Z", &task), Some(at(7, 0)));
+        // deadline reminders only apply once there is a deadline
+        assert_eq!(fire("deadline-1h", &task), None);
+        task.deadline = Some(at(17, 0));
+        assert_eq!(fire("deadline-1h", &task), Some(at(16, 0)));
+        assert_eq!(Reminder::defaults().iter().map(|r| r.fire_time(&task)).collect::<Vec<_>>(), [Some(at(8, 55)), Some(at(11, 58))]);
+    }
+
+    #[test]
+    fn messages_say_how_far_off_the_anchor_is() {
+        let task = report();
+        let message = |text: &str| text.parse::<Reminder>().unwrap().message(&task);
+        assert_eq!(message("start-5m"), "Reminder: 'Report' starts in 5 minutes!");
+        assert_eq!(message("end"), "Reminder: 'Report' ends now!");
+        assert_eq!(message("deadline+1h"), "Reminder: 'Report' was due 1 hour ago!");
+        assert_eq!(message("2030-01-01T07:00:00Z"), "Reminder: 'Report'");
+    }
+}
@@ -0,0 +1,262 @@
+use crate::error::TaskError;
+use crate::reminder::Reminder;
+use crate::shared::{Task, TaskStatus};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// One firing of one reminder of one task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReminderKey {
+    pub task_id: usize,
+    pub reminder: Reminder,
+    /// When the reminder was originally due, which tells occurrences of edited tasks apart
+    pub at: DateTime<Utc>,
+}
+
+impl ReminderKey {
+    pub fn new(task: &Task, reminder: &Reminder) -> Option<Self> {
+        Some(ReminderKey {
+            task_id: task.id,
+            reminder: reminder.clone(),
+            at: reminder.fire_time(task)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snooze {
+    pub key: ReminderKey,
+    pub until: DateTime<Utc>,
+}
+
+/// Snoozed and acknowledged reminders, shared between the commands and the daemon through a JSON file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderState {
+    pub acked: Vec<ReminderKey>,
+    pub snoozed: Vec<Snooze>,
+}
+
+impl ReminderState {
+    pub fn load(path: &str) -> Result<ReminderState, TaskError> {
+        if !Path::new(path).exists() {
+            return Ok(ReminderState::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), TaskError> {
+        // Written next to the file and renamed over it, as the daemon may read it at any time
+        let temp_path = format!("{}.tmp", path);
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn is_acked(&self, key: &ReminderKey) -> bool {
+        self.acked.contains(key)
+    }
+
+    pub fn snoozed_until(&self, key: &ReminderKey) -> Option<DateTime<Utc>> {
+        self.snoozed.iter().find(|snooze| snooze.key == *key).map(|snooze| snooze.until)
+    }
+
+    /// Fire the reminder again at `until` instead of never
+    pub fn snooze(&mut self, key: ReminderKey, until: DateTime<Utc>) {
+        self.acked.retain(|acked| *acked != key);
+        self.unsnooze(&key);
+        self.snoozed.push(Snooze { key, until });
+    }
+
+    /// Silence the reminder, including any pending snooze
+    pub fn ack(&mut self, key: ReminderKey) {
+        self.unsnooze(&key);
+        if !self.is_acked(&key) {
+            self.acked.push(key);
+        }
+    }
+
+    pub fn unsnooze(&mut self, key: &ReminderKey) {
+        self.snoozed.retain(|snooze| snooze.key != *key);
+    }
+
+    /// This state with another command's changes since `base` folded in, `saved` being what
+    /// that command wrote. Acknowledgements from either side are kept unless one side took
+    /// one back; when both sides changed a snooze the later one wins, so a new snooze is not
+    /// lost to one used up here.
+    pub fn merge(&self, base: &ReminderState, saved: ReminderState) -> ReminderState {
+        let mut acked: Vec<ReminderKey> = saved.acked.clone();
+        for key in &self.acked {
+            if !base.is_acked(key) && !acked.contains(key) {
+                acked.push(key.clone());
+            }
+        }
+        acked.retain(|key| self.is_acked(key) || !base.is_acked(key));
+
+        let mut merged = ReminderState { acked, snoozed: Vec::new() };
+        let keys = self.snoozed.iter().chain(&saved.snoozed).chain(&base.snoozed).map(|snooze| &snooze.key);
+        for key in keys {
+            if merged.is_acked(key) || merged.snoozed.iter().any(|snooze| snooze.key == *key) {
+                continue;
+            }
+            let (ours, theirs, was) = (self.snoozed_until(key), saved.snoozed_until(key), base.snoozed_until(key));
+            let until = if ours == was {
+                theirs
+            } else if theirs == was {
+                ours
+            } else {
+                ours.max(theirs)
+            };
+            if let Some(until) = until {
+                merged.snoozed.push(Snooze { key: key.clone(), until });
+            }
+        }
+        merged
+    }
+
+    /// Forget reminders of tasks that were deleted or are no longer pending
+    pub fn prune(&mut self, tasks: &DashMap<usize, Task>) {
+        let pending = |id: &usize| tasks.get(id).is_some_and(|task| task.status == TaskStatus::Pending);
+        self.acked.retain(|key| pending(&key.task_id));
+        self.snoozed.retain(|snooze| pending(&snooze.key.task_id));
+    }
+
+    /// The reminder of `task` that went off most recently at or before `now`, counting snoozes,
+    /// or the specific `reminder` when one is given
+    pub fn target(&self, task: &Task, reminders: &[Reminder], reminder: Option<&Reminder>, now: DateTime<Utc>) -> Result<ReminderKey, TaskError> {
+        if let Some(reminder) = reminder {
+            return ReminderKey::new(task, reminder).ok_or_else(|| {
+                TaskError::InvalidTime(format!("reminder '{}' does not apply to task {}", reminder, task.id))
+            });
+        }
+        reminders
+            .iter()
+            .filter_map(|reminder| ReminderKey::new(task, reminder))
+            .filter(|key| !self.is_acked(key))
+            .filter_map(|key| {
+                let went_off = self.snoozed_until(&key).unwrap_or(key.at);
+                (went_off <= now).then_some((went_off, key))
+            })
+            .max_by_key(|(went_off, _)| *went_off)
+            .map(|(_, key)| key)
+            .ok_or(TaskError::NoReminder(task.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    fn key(task_id: usize) -> ReminderKey {
+        ReminderKey { task_id, reminder: "start-5m".parse().unwrap(), at: at(8, 55) }
+    }
+
+    fn task(id: usize, start: DateTime<Utc>) -> Task {
+        let mut task = Task::new("Standup".into(), String::new(), start, start + chrono::Duration::minutes(15), false, None, None);
+        task.id = id;
+        task
+    }
+
+    #[test]
+    fn keys_tell_occurrences_and_reminders_apart() {
+        let start: Reminder = "start-5m".parse().unwrap();
+        let end: Reminder = "end-2m".parse().unwrap();
+        assert_eq!(ReminderKey::new(&task(1, at(9, 0)), &start), Some(key(1)));
+        assert_ne!(ReminderKey::new(&task(1, at(10, 0)), &start), Some(key(1)));
+        assert_ne!(ReminderKey::new(&task(1, at(9, 0)), &end), Some(key(1)));
+        assert_eq!(ReminderKey::new(&task(1, at(9, 0)), &"deadline-1h".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn snoozing_and_acknowledging_replace_each_other() {
+        let mut state = ReminderState::default();
+        state.snooze(key(1), at(9, 5));
+        state.snooze(key(1), at(9, 10));
+        assert_eq!(state.snoozed, [Snooze { key: key(1), until: at(9, 10) }]);
+        assert!(!state.is_acked(&key(1)));
+
+        state.ack(key(1));
+        state.ack(key(1));
+        assert_eq!(state.acked, [key(1)]);
+        assert_eq!(state.snoozed_until(&key(1)), None);
+
+        state.snooze(key(1), at(9, 20));
+        assert!(!state.is_acked(&key(1)));
+        assert_eq!(state.snoozed_until(&key(1)), Some(at(9, 20)));
+        assert_eq!(state.snoozed_until(&key(2)), None);
+    }
+
+    #[test]
+    fn target_is_the_reminder_that_went_off_last() {
+        let task = task(1, at(9, 0));
+        let reminders: Vec<Reminder> = vec!["start-15m".parse().unwrap(), "start-5m".parse().unwrap()];
+        let mut state = ReminderState::default();
+        assert_eq!(state.target(&task, &reminders, None, at(8, 50)).unwrap().at, at(8, 45));
+        assert_eq!(state.target(&task, &reminders, None, at(8, 56)).unwrap(), key(1));
+
+        // a snoozed reminder counts from the end of its snooze, acknowledged ones not at all
+        state.snooze(key(1), at(9, 10));
+        assert_eq!(state.target(&task, &reminders, None, at(8, 56)).unwrap().at, at(8, 45));
+        state.ack(ReminderKey::new(&task, &reminders[0]).unwrap());
+        assert!(matches!(state.target(&task, &reminders, None, at(8, 56)), Err(TaskError::NoReminder(1))));
+        assert_eq!(state.target(&task, &reminders, None, at(9, 10)).unwrap(), key(1));
+        assert_eq!(state.target(&task, &reminders, Some(&reminders[1]), at(8, 0)).unwrap(), key(1));
+    }
+
+    #[test]
+    fn saves_and_loads_round_trip() {
+        let path = std::env::temp_dir().join(format!("todo_task_reminders_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert_eq!(ReminderState::load(path).unwrap(), ReminderState::default());
+
+        let mut state = ReminderState::default();
+        state.snooze(key(1), at(9, 5));
+        state.ack(key(2));
+        state.save(path).unwrap();
+        let loaded = ReminderState::load(path);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.unwrap(), state);
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+    }
+
+    #[test]
+    fn merging_keeps_changes_from_both_sides() {
+        let mut base = ReminderState::default();
+        base.snooze(key(1), at(9, 5));
+        base.snooze(key(2), at(9, 5));
+
+        // the daemon used up both snoozes, while `snooze` and `ack` ran elsewhere
+        let mut daemon = base.clone();
+        daemon.unsnooze(&key(1));
+        daemon.unsnooze(&key(2));
+        let mut saved = base.clone();
+        saved.snooze(key(2), at(9, 30));
+        saved.ack(key(3));
+
+        let merged = daemon.merge(&base, saved.clone());
+        assert_eq!(merged.snoozed_until(&key(1)), None);
+        assert_eq!(merged.snoozed_until(&key(2)), Some(at(9, 30)));
+        assert!(merged.is_acked(&key(3)));
+
+        // an acknowledgement silences a snooze the other side still has
+        let mut acked = base.clone();
+        acked.ack(key(1));
+        let merged = acked.merge(&base, base.clone());
+        assert!(merged.is_acked(&key(1)));
+        assert_eq!(merged.snoozed_until(&key(1)), None);
+        assert_eq!(merged.snoozed_until(&key(2)), Some(at(9, 5)));
+    }
+}
@@ -0,0 +1,511 @@
+use crate::config::{Config, Zone};
+use crate::error::TaskError;
+use crate::notify::{dispatch, Notification, Notifier};
+use crate::overdue::overdue_message;
+use crate::quiet_hours::{quiet_until, QuietPolicy};
+use crate::reminder_state::{ReminderKey, ReminderState};
+use crate::shared::{AppState, Task, TaskStatus};
+
+use chrono::{DateTime, Utc};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// One of the task's reminders, by position in its effective reminder list
+    Reminder(usize),
+    /// A reminder that fell within quiet hours, moved to their end
+    HeldReminder(usize),
+    /// The end time of a recurring task, when its next occurrence is generated
+    End,
+    /// The task is still pending at its due time (level 0) or at one of the escalation follow-ups
+    Overdue(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub at: DateTime<Utc>,
+    pub task_id: usize,
+    pub kind: EventKind,
+}
+
+// Heap entry; `generation` ties it to one scheduling of its task so that
+// cancelled entries can be dropped lazily when they reach the top
+#[derive(Debug, PartialEq, Eq)]
+struct Entry {
+    at: DateTime<Utc>,
+    seq: u64,
+    generation: u64,
+    task_id: usize,
+    kind: EventKind,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// All pending timer events of all tasks, ordered by fire time.
+/// Rescheduling or cancelling a task is O(1); stale entries are skipped when popped.
+#[derive(Debug, Default)]
+pub struct TimerQueue {
+    heap: BinaryHeap<Reverse<Entry>>,
+    generations: HashMap<usize, u64>,
+    next_seq: u64,
+    next_generation: u64,
+}
+
+impl TimerQueue {
+    pub fn new() -> Self {
+        TimerQueue::default()
+    }
+
+    /// Replace whatever was scheduled for the task with the given events
+    pub fn schedule(&mut self, task_id: usize, events: Vec<(DateTime<Utc>, EventKind)>) {
+        self.next_generation += 1;
+        let generation = self.next_generation;
+        self.generations.insert(task_id, generation);
+        for (at, kind) in events {
+            self.next_seq += 1;
+            self.heap.push(Reverse(Entry {
+                at,
+                seq: self.next_seq,
+                generation,
+                task_id,
+                kind,
+            }));
+        }
+    }
+
+    pub fn cancel(&mut self, task_id: usize) {
+        self.generations.remove(&task_id);
+    }
+
+    /// When the earliest live event is due
+    pub fn next_fire_time(&mut self) -> Option<DateTime<Utc>> {
+        self.drop_stale();
+        self.heap.peek().map(|Reverse(entry)| entry.at)
+    }
+
+    /// Remove and return every live event due at or before `now`, earliest first
+    pub fn pop_due(&mut self, now: DateTime<Utc>) -> Vec<Event> {
+        let mut due = Vec::new();
+        while let Some(at) = self.next_fire_time() {
+            if at > now {
+                break;
+            }
+            if let Some(Reverse(entry)) = self.heap.pop() {
+                due.push(Event {
+                    at: entry.at,
+                    task_id: entry.task_id,
+                    kind: entry.kind,
+                });
+            }
+        }
+        due
+    }
+
+    fn drop_stale(&mut self) {
+        while let Some(Reverse(entry)) = self.heap.peek() {
+            if self.generations.get(&entry.task_id) == Some(&entry.generation) {
+                break;
+            }
+            self.heap.pop();
+        }
+    }
+}
+
+// Acknowledged reminders are left out, snoozed ones move to the end of their snooze
+// and ones within quiet hours to the end of the quiet period. Overdue alerts follow the
+// configured escalation; they stop once the task is no longer pending.
+fn task_events(
+    task: &Task,
+    config: &Config,
+    reminder_state: &ReminderState,
+    zone: Zone,
+    now: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, EventKind)> {
+    config
+        .reminders_for(task)
+        .iter()
+        .enumerate()
+        .filter_map(|(index, reminder)| {
+            let key = ReminderKey::new(task, reminder)?;
+            if reminder_state.is_acked(&key) {
+                return None;
+            }
+            let at = reminder_state.snoozed_until(&key).unwrap_or(key.at);
+            match quiet_until(&config.quiet_hours, zone, at) {
+                Some(end) => Some((end, EventKind::HeldReminder(index))),
+                None => Some((at, EventKind::Reminder(index))),
+            }
+        })
+        .chain(task.is_recurring.then_some((task.end_time, EventKind::End)))
+        .chain(
+            config
+                .escalation
+                .alert_times(task)
+                .into_iter()
+                .enumerate()
+                .map(|(level, at)| (at, EventKind::Overdue(level))),
+        )
+        .filter(|(at, _)| *at > now)
+        .collect()
+}
+
+/// Drives reminders, overdue alerts and recurrences for every task in the state from a single timer queue
+pub struct Scheduler {
+    state: Arc<AppState>,
+    config: Config,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    reminder_state: ReminderState,
+    queue: TimerQueue,
+    // Task as it was when its events were queued, to notice edits and deletions
+    scheduled: HashMap<usize, Task>,
+}
+
+impl Scheduler {
+    pub fn new(state: Arc<AppState>, config: Config, notifiers: Vec<Arc<dyn Notifier>>) -> Self {
+        Scheduler {
+            state,
+            config,
+            notifiers,
+            reminder_state: ReminderState::default(),
+            queue: TimerQueue::new(),
+            scheduled: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    pub fn next_fire_time(&mut self) -> Option<DateTime<Utc>> {
+        self.queue.next_fire_time()
+    }
+
+    pub fn reminder_state(&self) -> &ReminderState {
+        &self.reminder_state
+    }
+
+    /// Take over snoozes and acknowledgements made elsewhere; every task is rescheduled on the next sync
+    pub fn set_reminder_state(&mut self, reminder_state: ReminderState) {
+        self.reminder_state = reminder_state;
+        for id in self.scheduled.keys() {
+            self.queue.cancel(*id);
+        }
+        self.scheduled.clear();
+    }
+
+    /// Bring the queue in line with the state: queue new tasks, reschedule edited ones
+    /// and cancel the ones that were deleted or are no longer pending
+    pub fn sync(&mut self, now: DateTime<Utc>) {
+        let state = Arc::clone(&self.state);
+        self.reminder_state.prune(&state.tasks);
+        let queue = &mut self.queue;
+        self.scheduled.retain(|id, task| {
+            let unchanged = state.tasks.get(id).is_some_and(|current| current.value() == task);
+            if !unchanged {
+                queue.cancel(*id);
+            }
+            unchanged
+        });
+
+        for entry in state.tasks.iter() {
+            let task = entry.value();
+            if task.status != TaskStatus::Pending || self.scheduled.contains_key(&task.id) {
+                continue;
+            }
+            let events = task_events(task, &self.config, &self.reminder_state, self.state.calendar.zone, now);
+            if events.is_empty() {
+                continue;
+            }
+            self.queue.schedule(task.id, events);
+            self.scheduled.insert(task.id, task.clone());
+        }
+    }
+
+    /// Fire every event that is due at `now` and return them
+    pub async fn run_due(&mut self, now: DateTime<Utc>) -> Result<Vec<Event>, TaskError> {
+        let due = self.queue.pop_due(now);
+        let batch = self.config.quiet_policy == QuietPolicy::Batch;
+        let mut held: BTreeMap<DateTime<Utc>, Vec<(String, Task)>> = BTreeMap::new();
+        // hand-overs go last: they resync the queue, which would drop the other events
+        // of the same occurrence that are due at this moment
+        let (ends, rest): (Vec<&Event>, Vec<&Event>) = due.iter().partition(|event| event.kind == EventKind::End);
+        for event in rest.into_iter().chain(ends) {
+            match event.kind {
+                EventKind::HeldReminder(index) if batch => {
+                    if let Some(reminder) = self.take_reminder(event.task_id, index, true) {
+                        held.entry(event.at).or_default().push(reminder);
+                    }
+                }
+                _ => self.fire(event.clone()).await?,
+            }
+        }
+        for (at, reminders) in held {
+            self.notify_batch(at, reminders).await;
+        }
+        Ok(due)
+    }
+
+    // The message of a reminder that is going off, using up its snooze if it had one.
+    // Held reminders say when they were due, as their text may be out of date by now.
+    fn take_reminder(&mut self, task_id: usize, index: usize, held: bool) -> Option<(String, Task)> {
+        let task = self.scheduled.get(&task_id)?.clone();
+        let reminder = self.config.reminders_for(&task).get(index)?.clone();
+        let key = ReminderKey::new(&task, &reminder)?;
+        self.reminder_state.unsnooze(&key);
+        let mut message = reminder.message(&task);
+        if held {
+            let due = self.state.calendar.zone.to_local(key.at);
+            message = format!("{} (due {})", message, due.format("%Y-%m-%d %H:%M"));
+        }
+        Some((message, task))
+    }
+
+    // Reminders held back by quiet hours go out together as one "digest" notification
+    async fn notify_batch(&self, at: DateTime<Utc>, reminders: Vec<(String, Task)>) {
+        let Some((first_message, first_task)) = reminders.first() else {
+            return;
+        };
+        if reminders.len() == 1 {
+            self.notify("reminder", first_message.clone(), at, first_task).await;
+            return;
+        }
+        let lines: Vec<String> = reminders.iter().map(|(message, _)| format!("  {}", message)).collect();
+        let message = format!("{} reminders held during quiet hours:\n{}", reminders.len(), lines.join("\n"));
+        self.notify("digest", message, at, first_task).await;
+    }
+
+    async fn notify(&self, event: &str, message: String, at: DateTime<Utc>, task: &Task) {
+        let notification = Notification {
+            event: event.to_string(),
+            message,
+            at,
+            task: task.clone(),
+        };
+        dispatch(&self.notifiers, notification).await;
+    }
+
+    async fn fire(&mut self, event: Event) -> Result<(), TaskError> {
+        let Some(task) = self.scheduled.get(&event.task_id).cloned() else {
+            return Ok(());
+        };
+        match event.kind {
+            EventKind::Reminder(index) | EventKind::HeldReminder(index) => {
+                let held = matches!(event.kind, EventKind::HeldReminder(_));
+                if let Some((message, task)) = self.take_reminder(task.id, index, held) {
+                    self.notify("reminder", message, event.at, &task).await;
+                }
+            }
+            EventKind::End => {
+                // schedule the next instance of the recurring task
+                // (completion-relative tasks are continued by the `done` command instead)
+                if let Some(next_task) = task.next_instance(&self.state.calendar) {
+                    let start = next_task.start_time;
+                    if let Some(task_id) = self.state.hand_over(task.id, Some(next_task)).await? {
+                        println!("Next recurring task scheduled with ID: {} at {}", task_id, start);
+                    }
+                    self.sync(event.at);
+                }
+            }
+            EventKind::Overdue(level) => {
+                let mut task = task;
+                let mut message = overdue_message(&task, event.at);
+                if level > 0 && self.config.escalation.bump_priority && task.priority != task.priority.bump() {
+                    task.priority = task.priority.bump();
+                    // the queued copy is updated too, so the remaining follow-ups stay scheduled
+                    if let Some(mut entry) = self.state.tasks.get_mut(&task.id) {
+                        entry.value_mut().priority = task.priority;
+                    }
+                    self.scheduled.insert(task.id, task.clone());
+                    message = format!("{}, priority raised to {}", message, task.priority);
+                }
+                self.notify("overdue", message, event.at, &task).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflicts::ConflictPolicy;
+    use crate::holidays::WorkCalendar;
+    use crate::quiet_hours::QuietHours;
+    use crate::shared::Priority;
+    use chrono::{Duration, FixedOffset, TimeZone};
+    use std::sync::Mutex;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn queue_pops_due_events_in_time_order() {
+        let mut queue = TimerQueue::new();
+        queue.schedule(1, vec![(at(9, 30), EventKind::End), (at(9, 0), EventKind::Reminder(0))]);
+        queue.schedule(2, vec![(at(9, 15), EventKind::Reminder(0))]);
+
+        assert_eq!(queue.next_fire_time(), Some(at(9, 0)));
+        let due: Vec<(usize, DateTime<Utc>)> = queue.pop_due(at(9, 20)).iter().map(|event| (event.task_id, event.at)).collect();
+        assert_eq!(due, [(1, at(9, 0)), (2, at(9, 15))]);
+        assert_eq!(queue.next_fire_time(), Some(at(9, 30)));
+    }
+
+    #[test]
+    fn rescheduling_a_task_drops_its_earlier_events() {
+        let mut queue = TimerQueue::new();
+        queue.schedule(1, vec![(at(9, 0), EventKind::Reminder(0)), (at(10, 0), EventKind::End)]);
+        queue.schedule(1, vec![(at(11, 0), EventKind::End)]);
+
+        assert_eq!(queue.next_fire_time(), Some(at(11, 0)));
+        let due = queue.pop_due(at(12, 0));
+        assert_eq!(due, [Event { at: at(11, 0), task_id: 1, kind: EventKind::End }]);
+    }
+
+    #[test]
+    fn cancelled_tasks_never_fire() {
+        let mut queue = TimerQueue::new();
+        queue.schedule(1, vec![(at(9, 0), EventKind::Reminder(0))]);
+        queue.schedule(2, vec![(at(10, 0), EventKind::Reminder(0))]);
+        queue.cancel(1);
+
+        assert_eq!(queue.next_fire_time(), Some(at(10, 0)));
+        assert!(queue.pop_due(at(9, 30)).is_empty());
+        queue.cancel(2);
+        assert_eq!(queue.next_fire_time(), None);
+    }
+
+    #[tokio::test]
+    async fn end_of_a_recurring_task_hands_the_chain_over() {
+        let folder = std::env::temp_dir().join("todo_task_tests");
+        let state = Arc::new(
+            AppState::new(folder.to_string_lossy().into_owned(), WorkCalendar::default(), ConflictPolicy::Allow).detached(),
+        );
+        let rule = "FREQ=DAILY".parse().unwrap();
+        let task = Task::new("Standup".into(), String::new(), at(9, 0), at(9, 15), true, None, Some(rule));
+        let head = state.insert_task(task).await.unwrap();
+
+        let mut scheduler = Scheduler::new(Arc::clone(&state), Config::default(), Vec::new());
+        scheduler.sync(at(8, 0));
+        scheduler.run_due(at(9, 15)).await.unwrap();
+
+        let recurring: Vec<Task> = state.tasks.iter().filter(|entry| entry.is_recurring).map(|entry| entry.value().clone()).collect();
+        assert_eq!(recurring.len(), 1);
+        assert_ne!(recurring[0].id, head);
+        assert_eq!(recurring[0].start_time, at(9, 0) + Duration::days(1));
+        assert_eq!(recurring[0].series(), head);
+
+        // only the new head can skip ahead; the finished occurrence no longer drives the series
+        assert!(matches!(state.skip_next(head, at(9, 30)).await, Err(TaskError::NotRecurring(id)) if id == head));
+        let skipped = state.skip_next(recurring[0].id, at(9, 30)).await.unwrap();
+        assert_eq!(skipped, at(9, 0) + Duration::days(1));
+    }
+
+    // Keeps every notification it is handed
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Notification>>);
+
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+            self.0.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    // Two tasks whose reminders fall in the night and one in the morning
+    async fn quiet_night(policy: QuietPolicy) -> (Scheduler, Arc<Recorder>) {
+        let calendar = WorkCalendar { zone: Zone::Fixed(FixedOffset::east_opt(0).unwrap()), ..WorkCalendar::default() };
+        let folder = std::env::temp_dir().join("todo_task_tests");
+        let state = Arc::new(AppState::new(folder.to_string_lossy().into_owned(), calendar, ConflictPolicy::Allow).detached());
+        for (title, start) in [("Early run", at(5, 0)), ("Gym", at(6, 30)), ("Standup", at(10, 0))] {
+            let task = Task::new(title.into(), String::new(), start, start + Duration::minutes(30), false, None, None);
+            state.insert_task(task).await.unwrap();
+        }
+
+        let config = Config {
+            default_reminders: vec!["start-5m".parse().unwrap()],
+            quiet_hours: vec![QuietHours { days: Vec::new(), start: "22:00".parse().unwrap(), end: "07:00".parse().unwrap() }],
+            quiet_policy: policy,
+            ..Config::default()
+        };
+        let recorder = Arc::new(Recorder::default());
+        let mut scheduler = Scheduler::new(state, config, vec![Arc::clone(&recorder) as Arc<dyn Notifier>]);
+        scheduler.sync(at(0, 0));
+        (scheduler, recorder)
+    }
+
+    fn reminders(recorder: &Recorder) -> Vec<(String, DateTime<Utc>, String)> {
+        recorder
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|notification| notification.event != "overdue")
+            .map(|notification| (notification.event.clone(), notification.at, notification.message.clone()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_hold_reminders_until_they_end() {
+        let (mut scheduler, recorder) = quiet_night(QuietPolicy::Defer).await;
+        scheduler.run_due(at(6, 59)).await.unwrap();
+        assert!(reminders(&recorder).is_empty());
+
+        scheduler.run_due(at(7, 0)).await.unwrap();
+        // reminders released together keep no particular order
+        let mut sent = reminders(&recorder);
+        sent.sort();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0], ("reminder".into(), at(7, 0), "Reminder: 'Early run' starts in 5 minutes! (due 2030-01-01 04:55)".into()));
+        assert!(sent[1].2.starts_with("Reminder: 'Gym'") && sent[1].2.ends_with("(due 2030-01-01 06:25)"));
+
+        // outside quiet hours reminders go off on time
+        scheduler.run_due(at(9, 55)).await.unwrap();
+        assert_eq!(reminders(&recorder)[2], ("reminder".into(), at(9, 55), "Reminder: 'Standup' starts in 5 minutes!".into()));
+    }
+
+    #[tokio::test]
+    async fn batched_reminders_go_out_as_one_digest() {
+        let (mut scheduler, recorder) = quiet_night(QuietPolicy::Batch).await;
+        scheduler.run_due(at(7, 0)).await.unwrap();
+        let sent = reminders(&recorder);
+        assert_eq!(sent.len(), 1);
+        let (event, when, message) = &sent[0];
+        assert_eq!((event.as_str(), *when), ("digest", at(7, 0)));
+        assert!(message.starts_with("2 reminders held during quiet hours:\n"));
+        assert!(message.contains("\n  Reminder: 'Early run' starts in 5 minutes! (due 2030-01-01 04:55)"));
+        assert!(message.contains("\n  Reminder: 'Gym' starts in 5 minutes! (due 2030-01-01 06:25)"));
+    }
+
+    #[tokio::test]
+    async fn follow_up_alerts_raise_the_priority() {
+        let (mut scheduler, recorder) = quiet_night(QuietPolicy::Defer).await;
+        scheduler.config.escalation.bump_priority = true;
+        let early_run = scheduler.state.tasks.iter().find(|entry| entry.title == "Early run").map(|entry| entry.id).unwrap();
+
+        // due at 05:30, then follow-ups at 06:30 and the next day
+        scheduler.run_due(at(6, 30)).await.unwrap();
+        let alerts: Vec<String> = recorder.0.lock().unwrap().iter().filter(|n| n.event == "overdue").map(|n| n.message.clone()).collect();
+        assert_eq!(alerts, ["Task 'Early run' is overdue", "Task 'Early run' is overdue by 1 hour, priority raised to high"]);
+        assert_eq!(scheduler.state.tasks.get(&early_run).unwrap().priority, Priority::High);
+
+        scheduler.run_due(at(5, 30) + Duration::days(1)).await.unwrap();
+        assert_eq!(scheduler.state.tasks.get(&early_run).unwrap().priority, Priority::Urgent);
+    }
+}
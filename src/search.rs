@@ -0,0 +1,298 @@
+use crate::shared::Task;
+
+use std::ops::Range;
+
+// Text shown on either side of a match in details and notes
+const CONTEXT_CHARS: usize = 30;
+
+/// Part of a task the search looks at, in order of importance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    Title,
+    Details,
+    Notes,
+}
+
+impl SearchField {
+    const ALL: [SearchField; 3] = [SearchField::Title, SearchField::Details, SearchField::Notes];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchField::Title => "title",
+            SearchField::Details => "details",
+            SearchField::Notes => "notes",
+        }
+    }
+
+    fn weight(self) -> f64 {
+        match self {
+            SearchField::Title => 3.0,
+            SearchField::Details => 2.0,
+            SearchField::Notes => 1.0,
+        }
+    }
+
+    fn text(self, task: &Task) -> &str {
+        match self {
+            SearchField::Title => &task.title,
+            SearchField::Details => &task.details,
+            SearchField::Notes => &task.notes,
+        }
+    }
+}
+
+/// A task that matched every search term
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub task: Task,
+    pub score: f64,
+    /// Each field that matched, as pieces of text flagged `true` where they matched
+    pub fragments: Vec<(SearchField, Vec<(String, bool)>)>,
+}
+
+/// Tasks matching every whitespace-separated term of `query`, best match first.
+/// A term matches a field as a case-insensitive substring, as a word with a typo or two,
+/// or as its letters in order within one word ("stndup" finds "Standup").
+pub fn search(tasks: &[Task], query: &str) -> Vec<SearchHit> {
+    let terms: Vec<Vec<char>> = query.split_whitespace().map(lowercase).collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits: Vec<SearchHit> = tasks.iter().filter_map(|task| score_task(task, &terms)).collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.task.id.cmp(&b.task.id)));
+    hits
+}
+
+fn score_task(task: &Task, terms: &[Vec<char>]) -> Option<SearchHit> {
+    let fields: Vec<(SearchField, Vec<char>)> = SearchField::ALL
+        .into_iter()
+        .map(|field| (field, lowercase(field.text(task))))
+        .collect();
+    let mut ranges: Vec<Vec<Range<usize>>> = vec![Vec::new(); fields.len()];
+    let mut score = 0.0;
+
+    for term in terms {
+        let mut best = 0.0_f64;
+        for (index, (field, text)) in fields.iter().enumerate() {
+            if let Some((quality, found)) = match_term(text, term) {
+                best = best.max(quality * field.weight());
+                ranges[index].extend(found);
+            }
+        }
+        if best == 0.0 {
+            return None;
+        }
+        score += best;
+    }
+
+    let fragments = fields
+        .iter()
+        .zip(ranges)
+        .filter(|(_, found)| !found.is_empty())
+        .map(|((field, _), found)| {
+            let text: Vec<char> = field.text(task).chars().collect();
+            (*field, highlight(&text, found, *field != SearchField::Title))
+        })
+        .collect();
+    Some(SearchHit { task: task.clone(), score, fragments })
+}
+
+// One char per original char so match positions line up with the original text
+fn lowercase(text: &str) -> Vec<char> {
+    text.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect()
+}
+
+fn words(text: &[char]) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.iter().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..text.len());
+    }
+    words
+}
+
+// How well `term` matches `text` (0 to 1) and where
+fn match_term(text: &[char], term: &[char]) -> Option<(f64, Vec<Range<usize>>)> {
+    let words = words(text);
+    let substrings: Vec<Range<usize>> = (0..(text.len() + 1).saturating_sub(term.len()))
+        .filter(|&i| text[i..i + term.len()] == *term)
+        .map(|i| i..i + term.len())
+        .collect();
+    if let Some(quality) = substrings
+        .iter()
+        .map(|found| {
+            let starts_word = words.iter().any(|word| word.start == found.start);
+            let whole_word = words.contains(found);
+            0.6 + if starts_word { 0.2 } else { 0.0 } + if whole_word { 0.2 } else { 0.0 }
+        })
+        .reduce(f64::max)
+    {
+        return Some((quality, substrings));
+    }
+
+    let allowed_typos = match term.len() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let mut best: Option<(f64, Vec<Range<usize>>)> = None;
+    for word in words {
+        let candidate = &text[word.clone()];
+        let quality = match edit_distance(candidate, term) {
+            distance if distance <= allowed_typos => 0.5 - 0.1 * distance as f64,
+            _ if term.len() > 2 && is_subsequence(term, candidate) => 0.3 * term.len() as f64 / candidate.len() as f64,
+            _ => continue,
+        };
+        match &mut best {
+            Some((top, found)) if *top == quality => found.push(word),
+            Some((top, _)) if *top > quality => {}
+            _ => best = Some((quality, vec![word])),
+        }
+    }
+    best
+}
+
+// Levenshtein distance where swapping two neighbouring letters also counts as one edit
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows: Vec<Vec<usize>> = vec![(0..=b.len()).collect()];
+    for i in 1..=a.len() {
+        let mut row = vec![i];
+        for j in 1..=b.len() {
+            let mut distance = (rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                .min(rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            row.push(distance);
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+fn is_subsequence(needle: &[char], haystack: &[char]) -> bool {
+    let mut rest = haystack.iter();
+    needle.iter().all(|c| rest.any(|h| h == c))
+}
+
+// Splits the text into matched and unmatched pieces; long text is cut down to the parts
+// around the matches
+fn highlight(text: &[char], mut found: Vec<Range<usize>>, trim: bool) -> Vec<(String, bool)> {
+    found.sort_by_key(|range| (range.start, range.end));
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in found {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    let mut pieces = Vec::new();
+    let mut cursor = 0;
+    for range in merged {
+        let gap = &text[cursor..range.start];
+        let mut plain = String::new();
+        if trim && cursor > 0 && gap.len() > 2 * CONTEXT_CHARS {
+            plain.extend(&gap[..CONTEXT_CHARS]);
+            plain.push('…');
+            plain.extend(&gap[gap.len() - CONTEXT_CHARS..]);
+        } else if trim && cursor == 0 && gap.len() > CONTEXT_CHARS {
+            plain.push('…');
+            plain.extend(&gap[gap.len() - CONTEXT_CHARS..]);
+        } else {
+            plain.extend(gap);
+        }
+        if !plain.is_empty() {
+            pieces.push((plain, false));
+        }
+        pieces.push((text[range.clone()].iter().collect(), true));
+        cursor = range.end;
+    }
+    let tail = &text[cursor..];
+    if trim && tail.len() > CONTEXT_CHARS {
+        pieces.push((tail[..CONTEXT_CHARS].iter().collect::<String>() + "…", false));
+    } else if !tail.is_empty() {
+        pieces.push((tail.iter().collect(), false));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn task(id: usize, title: &str, details: &str, notes: &str) -> Task {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        let mut task = Task::new(title.into(), details.into(), start, start + chrono::Duration::hours(1), false, None, None);
+        task.id = id;
+        task.notes = notes.into();
+        task
+    }
+
+    fn ranked(tasks: &[Task], query: &str) -> Vec<usize> {
+        search(tasks, query).into_iter().map(|hit| hit.task.id).collect()
+    }
+
+    #[test]
+    fn title_outranks_details_and_notes() {
+        let tasks = [
+            task(1, "Call", "", "report figures"),
+            task(2, "Write", "Quarterly report", ""),
+            task(3, "Quarterly report", "", ""),
+            task(4, "Lunch", "", ""),
+        ];
+        assert_eq!(ranked(&tasks, "report"), [3, 2, 1]);
+    }
+
+    #[test]
+    fn whole_words_outrank_prefixes_and_inner_matches() {
+        let tasks = [task(1, "Upstanding", "", ""), task(2, "Standup", "", ""), task(3, "Stand", "", "")];
+        assert_eq!(ranked(&tasks, "STAND"), [3, 2, 1]);
+    }
+
+    #[test]
+    fn typos_and_missing_letters_still_match() {
+        let tasks = [task(1, "Quarterly report", "", ""), task(2, "Deploy", "", ""), task(3, "Team standup", "", "")];
+        assert_eq!(ranked(&tasks, "quartrly"), [1]);
+        assert_eq!(ranked(&tasks, "stnadup"), [3]);
+        assert_eq!(ranked(&tasks, "dply"), [2]);
+        assert!(ranked(&tasks, "tem").contains(&3));
+        // two letters have to appear as they are
+        assert!(ranked(&tasks, "tm").is_empty());
+    }
+
+    #[test]
+    fn every_term_has_to_match() {
+        let tasks = [task(1, "Deploy", "release 2.0", ""), task(2, "Deploy", "hotfix", "")];
+        assert_eq!(ranked(&tasks, "deploy release"), [1]);
+        assert!(ranked(&tasks, "   ").is_empty());
+        // equal scores keep ID order
+        assert_eq!(ranked(&tasks, "deploy"), [1, 2]);
+    }
+
+    #[test]
+    fn fragments_mark_the_matches() {
+        let details = format!("{}budget review{}", "x".repeat(40), "y".repeat(40));
+        let hits = search(&[task(1, "Team budget", &details, "")], "budget");
+        assert_eq!(hits[0].fragments[0], (SearchField::Title, vec![("Team ".into(), false), ("budget".into(), true)]));
+
+        let (field, pieces) = &hits[0].fragments[1];
+        assert_eq!(*field, SearchField::Details);
+        assert_eq!(pieces[0], (format!("…{}", "x".repeat(CONTEXT_CHARS)), false));
+        assert_eq!(pieces[1], ("budget".into(), true));
+        assert_eq!(pieces[2], (format!(" review{}…", "y".repeat(CONTEXT_CHARS - 7)), false));
+    }
+}
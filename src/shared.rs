@@ -1,20 +1,24 @@
 
 use dashmap::DashMap;
+use crate::agenda::{self, Occurrence};
+use crate::conflicts::{overlaps, ConflictPolicy};
 use crate::error::TaskError;
 use crate::read_write::ReadWrite;
 use crate::ex_csv::Exportable;
+use crate::filter::Filter;
+use crate::holidays::{NonWorkingPolicy, WorkCalendar};
+use crate::recurrence::Recurrence;
+use crate::reminder::Reminder;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::io::{self, Write};
 use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
-use tokio::{sync::Mutex, time::sleep};
-//use std::path::Path;
-use std::time::Duration;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Task {
     pub id: usize,
     pub title: String,
@@ -23,6 +27,160 @@ pub struct Task {
     pub end_time: DateTime<Utc>,
     pub is_recurring: bool,
     pub frequency_minutes: Option<i64>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// Occurrence start times excluded from the series
+    #[serde(default)]
+    pub exdates: Vec<DateTime<Utc>>,
+    /// Zero-based position of this occurrence in its series, used for COUNT
+    #[serde(default)]
+    pub occurrence_index: u32,
+    /// ID of the first occurrence; shared by every occurrence generated from it
+    #[serde(default)]
+    pub series_id: Option<usize>,
+    /// Start time the series originally assigned to this occurrence, if it was moved off it,
+    /// either by editing it on its own or by shifting it off a non-working day
+    #[serde(default)]
+    pub original_start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub status: TaskStatus,
+    /// What to do with occurrences that passed while nothing was running
+    #[serde(default)]
+    pub catch_up: CatchUpPolicy,
+    /// Whether the next occurrence follows the schedule or the actual completion
+    #[serde(default)]
+    pub recur_from: RecurFrom,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the task was added; unset on occurrences generated by a series
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// What happens to occurrences landing on weekends or holidays
+    #[serde(default)]
+    pub non_working: NonWorkingPolicy,
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+    /// Reminders for this task; the configured defaults apply when empty
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Free-form labels such as "ops" or "home"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Longer free-form text kept alongside the details
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Pending,
+    Done,
+    Missed,
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TaskStatus::Pending => "pending",
+            TaskStatus::Done => "done",
+            TaskStatus::Missed => "missed",
+        };
+        f.pad(name)
+    }
+}
+
+/// How important a task is; overdue tasks can be bumped up automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    /// The next level up; urgent stays urgent
+    pub fn bump(self) -> Priority {
+        match self {
+            Priority::Low => Priority::Normal,
+            Priority::Normal => Priority::High,
+            Priority::High | Priority::Urgent => Priority::Urgent,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        };
+        f.pad(name)
+    }
+}
+
+/// What the next occurrence of a recurring task is computed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RecurFrom {
+    /// The fixed grid of the recurrence rule, starting at the first occurrence
+    #[default]
+    Schedule,
+    /// The moment the previous occurrence was marked done
+    Completion,
+}
+
+/// How missed occurrences of a recurring task are handled on startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Create a pending task for every missed occurrence
+    All,
+    /// Skip the missed occurrences and only create the next upcoming one
+    #[default]
+    Next,
+    /// Record the missed occurrences as missed
+    MarkMissed,
+}
+
+/// Outcome of catching up one recurring series
+#[derive(Debug)]
+pub struct CatchUpReport {
+    pub series_id: usize,
+    pub title: String,
+    pub policy: CatchUpPolicy,
+    pub missed: Vec<DateTime<Utc>>,
+    pub next: Option<(usize, DateTime<Utc>)>,
+}
+
+impl fmt::Display for CatchUpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.policy {
+            CatchUpPolicy::All => "created as pending",
+            CatchUpPolicy::Next => "skipped",
+            CatchUpPolicy::MarkMissed => "marked missed",
+        };
+        write!(
+            f,
+            "Caught up '{}' (series {}): {} missed occurrence(s) {}",
+            self.title,
+            self.series_id,
+            self.missed.len(),
+            action
+        )?;
+        match self.next {
+            Some((id, start)) => write!(f, ", next occurrence is task {} at {}", id, start),
+            None => write!(f, ", series has ended"),
+        }
+    }
 }
 
 pub struct AppState {
@@ -31,6 +189,11 @@ pub struct AppState {
     pub done_folder: String,
     pub exportable: Exportable,
     pub read_write: ReadWrite,
+    pub calendar: WorkCalendar,
+    /// Whether task files are written to the done folder; off for simulations
+    pub archive: bool,
+    /// How added or rescheduled tasks that overlap others are handled
+    pub conflict_policy: ConflictPolicy,
 }
 
 impl Task {
@@ -41,6 +204,7 @@ impl Task {
         end_time: DateTime<Utc>,
         is_recurring: bool,
         frequency_minutes: Option<i64>,
+        recurrence: Option<Recurrence>,
     ) -> Self {
         Task {
             id: 0,
@@ -50,21 +214,199 @@ impl Task {
             end_time,
             is_recurring,
             frequency_minutes,
+            recurrence,
+            exdates: Vec::new(),
+            occurrence_index: 0,
+            series_id: None,
+            original_start: None,
+            status: TaskStatus::Pending,
+            catch_up: CatchUpPolicy::default(),
+            recur_from: RecurFrom::default(),
+            completed_at: None,
+            created_at: None,
+            non_working: NonWorkingPolicy::default(),
+            deadline: None,
+            reminders: Vec::new(),
+            priority: Priority::default(),
+            tags: Vec::new(),
+            notes: String::new(),
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+    }
+
+    /// When the task has to be done by: its deadline, or its end time without one
+    pub fn due(&self) -> DateTime<Utc> {
+        self.deadline.unwrap_or(self.end_time)
+    }
+
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.status == TaskStatus::Pending && self.due() <= now
+    }
+
+    /// Series this task belongs to; a standalone task is its own series
+    pub fn series(&self) -> usize {
+        self.series_id.unwrap_or(self.id)
+    }
+
+    /// The rule driving this task's recurrence; an explicit RRULE wins over `frequency_minutes`
+    pub fn recurrence_rule(&self) -> Option<Recurrence> {
+        if !self.is_recurring {
+            return None;
         }
+        self.recurrence
+            .clone()
+            .or_else(|| self.frequency_minutes.map(Recurrence::every_minutes))
+    }
+
+    /// Build the next occurrence of a recurring task, keeping its duration.
+    /// Excluded dates and occurrences dropped for falling on non-working days are
+    /// skipped but still count towards the rule's COUNT.
+    /// Completion-relative tasks only have a next occurrence once they are done.
+    pub fn next_instance(&self, calendar: &WorkCalendar) -> Option<Task> {
+        let anchor = match self.recur_from {
+            RecurFrom::Schedule => self.original_start.unwrap_or(self.start_time),
+            RecurFrom::Completion => self.completed_at?.trunc_subsecs(0),
+        };
+        let rule = self.recurrence_rule()?;
+        let mut slot = anchor;
+        let mut index = self.occurrence_index;
+
+        let start = loop {
+            slot = rule.next_after(anchor, slot, calendar.zone)?;
+            index += 1;
+            if !rule.allows_index(index) {
+                return None;
+            }
+            if self.exdates.contains(&slot) {
+                continue;
+            }
+            match calendar.adjust(slot, self.non_working) {
+                Some(start) if start > self.start_time => break start,
+                _ => continue,
+            }
+        };
+
+        Some(Task {
+            id: 0,
+            start_time: start,
+            end_time: start + (self.end_time - self.start_time),
+            deadline: self.deadline.map(|deadline| start + (deadline - self.start_time)),
+            occurrence_index: index,
+            series_id: Some(self.series()),
+            // Later occurrences are computed from the slot, not from the shifted time
+            original_start: (start != slot && self.recur_from == RecurFrom::Schedule).then_some(slot),
+            status: TaskStatus::Pending,
+            completed_at: None,
+            created_at: None,
+            ..self.clone()
+        })
     }
 }
 
+/// Which occurrences of a recurring series an edit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EditScope {
+    /// Only the selected occurrence
+    This,
+    /// The selected occurrence and all later ones
+    Future,
+    /// Every occurrence in the series
+    Series,
+}
+
+#[derive(Clone, Default)]
 pub struct TaskUpdate {
     pub title: Option<String>,
     pub details: Option<String>,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
+    /// Moves the end time to this long after the (possibly new) start time
+    pub length: Option<Duration>,
     pub is_recurring: Option<bool>,
     pub frequency_minutes: Option<i64>,
+    pub recurrence: Option<Recurrence>,
+    pub exdates: Vec<DateTime<Utc>>,
+    pub catch_up: Option<CatchUpPolicy>,
+    pub recur_from: Option<RecurFrom>,
+    pub non_working: Option<NonWorkingPolicy>,
+    pub deadline: Option<DateTime<Utc>>,
+    /// Replaces the task's reminders when set
+    pub reminders: Option<Vec<Reminder>>,
+    pub priority: Option<Priority>,
+    /// Replaces the task's tags when set
+    pub tags: Option<Vec<String>>,
+    pub notes: Option<String>,
+}
+
+impl TaskUpdate {
+    /// Whether the update moves the task in time
+    pub fn reschedules(&self) -> bool {
+        self.start_time.is_some() || self.end_time.is_some() || self.length.is_some()
+    }
+
+    pub fn apply(self, task: &mut Task) {
+        if let Some(title) = self.title {
+            task.title = title;
+        }
+        if let Some(details) = self.details {
+            task.details = details;
+        }
+        if let Some(start_time) = self.start_time {
+            task.start_time = start_time;
+        }
+        if let Some(end_time) = self.end_time {
+            task.end_time = end_time;
+        }
+        if let Some(length) = self.length {
+            task.end_time = task.start_time + length;
+        }
+        if let Some(is_recurring) = self.is_recurring {
+            task.is_recurring = is_recurring;
+        }
+        if let Some(frequency_minutes) = self.frequency_minutes {
+            task.frequency_minutes = Some(frequency_minutes);
+        }
+        if let Some(recurrence) = self.recurrence {
+            task.recurrence = Some(recurrence);
+            task.is_recurring = true;
+        }
+        for exdate in self.exdates {
+            if !task.exdates.contains(&exdate) {
+                task.exdates.push(exdate);
+            }
+        }
+        if let Some(catch_up) = self.catch_up {
+            task.catch_up = catch_up;
+        }
+        if let Some(recur_from) = self.recur_from {
+            task.recur_from = recur_from;
+        }
+        if let Some(non_working) = self.non_working {
+            task.non_working = non_working;
+        }
+        if let Some(deadline) = self.deadline {
+            task.deadline = Some(deadline);
+        }
+        if let Some(reminders) = self.reminders {
+            task.reminders = reminders;
+        }
+        if let Some(priority) = self.priority {
+            task.priority = priority;
+        }
+        if let Some(tags) = self.tags {
+            task.tags = tags;
+        }
+        if let Some(notes) = self.notes {
+            task.notes = notes;
+        }
+    }
 }
 
 impl AppState {
-    pub fn new(done_folder: String) -> Self {
+    pub fn new(done_folder: String, calendar: WorkCalendar, conflict_policy: ConflictPolicy) -> Self {
         fs::create_dir_all(&done_folder).unwrap();
         let tasks = Arc::new(DashMap::new());
         AppState {
@@ -73,26 +415,53 @@ impl AppState {
             tasks: Arc::clone(&tasks),
             next_id: Arc::new(AtomicUsize::new(1)),
             done_folder,
+            calendar,
+            archive: true,
+            conflict_policy,
         }
     }
 
-    pub async fn add_task(&self, mut task: Task) -> Result<usize, TaskError> {
+    /// An in-memory copy of the tasks that never touches the disk, for rehearsing what would happen
+    pub fn detached(&self) -> AppState {
+        let tasks = Arc::new(DashMap::new());
+        for entry in self.tasks.iter() {
+            tasks.insert(*entry.key(), entry.value().clone());
+        }
+        AppState {
+            read_write: ReadWrite { tasks: tasks.clone() },
+            exportable: Exportable::new(Arc::clone(&tasks)),
+            tasks,
+            next_id: Arc::new(AtomicUsize::new(self.next_id.load(Ordering::SeqCst))),
+            done_folder: self.done_folder.clone(),
+            calendar: self.calendar.clone(),
+            archive: false,
+            conflict_policy: self.conflict_policy,
+        }
+    }
+
+    /// Add a task entered by the user, checking it against the conflict policy
+    pub async fn add_task(&self, task: Task) -> Result<usize, TaskError> {
+        self.check_conflicts(&task, &[])?;
+        self.insert_task(task).await
+    }
+
+    /// Add a task without the conflict check, for occurrences generated from a series
+    pub async fn insert_task(&self, mut task: Task) -> Result<usize, TaskError> {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
         task.id = id;
+        if task.is_recurring && task.series_id.is_none() {
+            task.series_id = Some(id);
+        }
         self.tasks.insert(id, task.clone());
         self.save_task_to_file(&task).await?;
 
-        let tasks_clone = Arc::new(Mutex::new(self.tasks.iter().map(|entry| (entry.key().clone(), entry.value().clone())).collect::<HashMap<_, _>>()));
-        let done_folder_clone = self.done_folder.clone();
-        let task_clone = task.clone();
-        
-        tokio::spawn(async move {
-            AppState::monitor_task(tasks_clone, done_folder_clone, task_clone).await;
-        });
-            Ok(id)
+        Ok(id)
     }
 
     pub async fn save_task_to_file(&self, task: &Task) -> io::Result<()> {
+        if !self.archive {
+            return Ok(());
+        }
         let category_path = format!("{}/{}", self.done_folder, task.title);
         
         fs::create_dir_all(&category_path)?;
@@ -101,31 +470,14 @@ impl AppState {
         let mut file = fs::File::create(&filename)?;
         writeln!(
             file,
-            "- ID: {}\n  Title: {}\n  Details: {}\n  Start: {}\n  End: {}\n  Recurring: {}\n  Frequency: {:?}",
-            task.id, task.title, task.details, task.start_time, task.end_time, task.is_recurring, task.frequency_minutes
+            "- ID: {}\n  Title: {}\n  Details: {}\n  Start: {}\n  End: {}\n  Recurring: {}\n  Frequency: {:?}\n  Recurrence: {}\n  Status: {:?}\n  Completed: {}\n  Priority: {}\n  Tags: {}\n  Notes: {}",
+            task.id, task.title, task.details, task.start_time, task.end_time, task.is_recurring, task.frequency_minutes,
+            task.recurrence.as_ref().map(|r| r.to_string()).unwrap_or_default(), task.status,
+            task.completed_at.map(|t| t.to_string()).unwrap_or_default(), task.priority, task.tags.join(", "), task.notes
         )?;
         Ok(())
     }
 
-    async fn monitor_task(tasks: Arc<Mutex<HashMap<usize, Task>>>, done_folder: String, task: Task) {
-        let duration = (task.end_time - Utc::now()).to_std().unwrap_or(Duration::from_secs(0));
-        sleep(duration).await;
-        let mut tasks = tasks.lock().await;
-        if tasks.remove(&task.id).is_some() {
-            let category_path = format!("{}/{}", done_folder, task.title);
-            fs::create_dir_all(&category_path).unwrap();
-
-            let filename = format!("{}/task_{}.txt", category_path, task.id);
-            let mut file = fs::File::create(&filename).unwrap();
-            writeln!(
-                file,
-                "- ID: {}\n  Title: {}\n  Details: {}\n  Start: {}\n  End: {}\n  Recurring: {}\n  Frequency: {:?}",
-                task.id, task.title, task.details, task.start_time, task.end_time, task.is_recurring, task.frequency_minutes
-            ).unwrap();
-            println!("Task {} moved to done folder.", task.id);
-        }
-    }
-
     pub async fn delete_task(&self, id: usize) -> Result<(), TaskError> {
     if let Some((_, task)) = self.tasks.remove(&id) { // Destructure the tuple
         let category_path = format!("{}/{}", self.done_folder, task.title);
@@ -154,49 +506,577 @@ impl AppState {
     }
 
     pub async fn edit_task(&self, id: usize, update: TaskUpdate) -> Result<(), TaskError> {
+        let task = self.list_tasks_by_id(id).await.ok_or(TaskError::NotFound(id))?;
+        self.check_update(&task, &update)?;
+        self.apply_update(id, update).await
+    }
+
+    async fn apply_update(&self, id: usize, update: TaskUpdate) -> Result<(), TaskError> {
         if let Some(mut task_entry) = self.tasks.get_mut(&id) {
-             let task = task_entry.value_mut();
+            let task = task_entry.value_mut();
+            update.apply(task);
+            self.save_task_to_file(task).await?;
+            Ok(())
+        } else {
+            Err(TaskError::NotFound(id))
+        }
+    }
+
+    /// Pending tasks other than `ignore` scheduled at the same time as `task`, including
+    /// occurrences other series will create there
+    pub fn overlapping(&self, task: &Task, ignore: &[usize]) -> Vec<Occurrence> {
+        let tasks: Vec<Task> = self.tasks.iter().map(|entry| entry.value().clone()).collect();
+        agenda::occurrences(&tasks, &self.calendar, task.start_time, task.end_time)
+            .into_iter()
+            .filter(|other| {
+                if other.projected {
+                    // the task's own series moves along with it
+                    task.series_id.is_none_or(|series| series != other.task.series())
+                } else {
+                    other.task.id != task.id && !ignore.contains(&other.task.id)
+                }
+            })
+            .filter(|other| other.task.status == TaskStatus::Pending && overlaps(task, &other.task))
+            .collect()
+    }
+
+    // Apply the conflict policy to where an update would move the task
+    fn check_update(&self, task: &Task, update: &TaskUpdate) -> Result<(), TaskError> {
+        if !update.reschedules() {
+            return Ok(());
+        }
+        let mut moved = task.clone();
+        update.clone().apply(&mut moved);
+        self.check_conflicts(&moved, &[])
+    }
+
+    // Apply the conflict policy to a task about to be added or moved
+    fn check_conflicts(&self, task: &Task, ignore: &[usize]) -> Result<(), TaskError> {
+        if self.conflict_policy == ConflictPolicy::Allow {
+            return Ok(());
+        }
+        let overlapping = self.overlapping(task, ignore);
+        let Some(first) = overlapping.first() else {
+            return Ok(());
+        };
+        if self.conflict_policy == ConflictPolicy::Refuse {
+            let title = first.task.title.clone();
+            return Err(if first.projected {
+                TaskError::UpcomingConflict { series: first.task.series(), title, start: first.task.start_time }
+            } else {
+                TaskError::Conflict { id: first.task.id, title }
+            });
+        }
+        for other in &overlapping {
+            let what = if other.projected {
+                format!("the upcoming occurrence of series {}", other.task.series())
+            } else {
+                format!("task {}", other.task.id)
+            };
+            eprintln!(
+                "Warning: '{}' overlaps with {} '{}' ({} - {})",
+                task.title, what, other.task.title, other.task.start_time, other.task.end_time
+            );
+        }
+        Ok(())
+    }
+
+    /// Edit a task together with the other occurrences of its series, as selected by `scope`.
+    /// Time changes are applied to every affected occurrence as a shift relative to the
+    /// edited one. Returns the IDs of the tasks that were updated.
+    pub async fn edit_series(&self, id: usize, update: TaskUpdate, scope: EditScope) -> Result<Vec<usize>, TaskError> {
+        let target = self.list_tasks_by_id(id).await.ok_or(TaskError::NotFound(id))?;
 
-            if let Some(title) = update.title {
-                task.title = title;
+        if scope == EditScope::This || target.series_id.is_none() {
+            if target.series_id.is_some() && target.is_recurring {
+                // checked before detaching, so a refused edit leaves the series as it was
+                self.check_update(&target, &update)?;
+                self.detach_occurrence(&target).await?;
+                self.apply_update(id, update).await?;
+            } else {
+                self.edit_task(id, update).await?;
             }
-            if let Some(details) = update.details {
-                task.details = details;
+            return Ok(vec![id]);
+        }
+
+        let series = target.series();
+        let mut members: Vec<(DateTime<Utc>, usize)> = self
+            .tasks
+            .iter()
+            .filter(|entry| entry.value().series() == series)
+            .filter(|entry| scope == EditScope::Series || entry.value().start_time >= target.start_time)
+            .map(|entry| (entry.value().start_time, *entry.key()))
+            .collect();
+        members.sort();
+
+        // Editing "this and future" occurrences splits them off into a new series
+        // headed by the edited task; earlier occurrences keep their edits and the old
+        // series' rule is ended before the split
+        let split = scope == EditScope::Future
+            && self
+                .tasks
+                .iter()
+                .any(|entry| entry.value().series() == series && entry.value().start_time < target.start_time);
+
+        let start_shift = update.start_time.map(|start| start - target.start_time);
+        let end_shift = update.end_time.map(|end| end - target.end_time);
+
+        if update.reschedules() {
+            let member_ids: Vec<usize> = members.iter().map(|(_, member_id)| *member_id).collect();
+            for member_id in &member_ids {
+                if let Some(mut moved) = self.tasks.get(member_id).map(|entry| entry.value().clone()) {
+                    moved.start_time = start_shift.map_or(moved.start_time, |shift| moved.start_time + shift);
+                    moved.end_time = end_shift.map_or(moved.end_time, |shift| moved.end_time + shift);
+                    if let Some(length) = update.length {
+                        moved.end_time = moved.start_time + length;
+                    }
+                    self.check_conflicts(&moved, &member_ids)?;
+                }
             }
-            if let Some(start_time) = update.start_time {
-                task.start_time = start_time;
+        }
+
+        let mut updated = Vec::with_capacity(members.len());
+        for (_, member_id) in members {
+            if let Some(mut task_entry) = self.tasks.get_mut(&member_id) {
+                let task = task_entry.value_mut();
+                let mut member_update = TaskUpdate {
+                    start_time: start_shift.map(|shift| task.start_time + shift),
+                    end_time: end_shift.map(|shift| task.end_time + shift),
+                    ..update.clone()
+                };
+                // Occurrences edited on their own stay detached from the rule
+                if !task.is_recurring {
+                    member_update.is_recurring = None;
+                    member_update.frequency_minutes = None;
+                    member_update.recurrence = None;
+                }
+                member_update.apply(task);
+                if let (Some(shift), Some(slot)) = (start_shift, task.original_start.as_mut()) {
+                    *slot += shift;
+                }
+                if split {
+                    task.series_id = Some(id);
+                }
+                self.save_task_to_file(task).await?;
+                updated.push(member_id);
             }
-            if let Some(end_time) = update.end_time {
-                task.end_time = end_time;
+        }
+        if split {
+            self.truncate_series(series, &target).await?;
+        }
+        Ok(updated)
+    }
+
+    // End the rule of the occurrences a split left behind just before `target`, so the
+    // old series no longer describes the occurrences the new one took over
+    async fn truncate_series(&self, series: usize, target: &Task) -> Result<(), TaskError> {
+        let earlier: Vec<usize> = self
+            .tasks
+            .iter()
+            .filter(|entry| entry.value().series() == series && entry.value().recurrence.is_some())
+            .map(|entry| *entry.key())
+            .collect();
+        for member_id in earlier {
+            if let Some(mut task_entry) = self.tasks.get_mut(&member_id) {
+                let task = task_entry.value_mut();
+                if let Some(rule) = task.recurrence.as_mut() {
+                    if rule.count.is_some() {
+                        rule.count = Some(target.occurrence_index);
+                    } else {
+                        let last = target.start_time - Duration::seconds(1);
+                        rule.until = Some(rule.until.map_or(last, |until| until.min(last)));
+                    }
+                }
+                self.save_task_to_file(task).await?;
             }
-            if let Some(is_recurring) = update.is_recurring {
-                task.is_recurring = is_recurring;
+        }
+        Ok(())
+    }
+
+    /// Mark a task as done. For completion-relative recurrences this is what creates the
+    /// next occurrence; returns its ID and start time if one was created.
+    pub async fn complete_task(&self, id: usize, now: DateTime<Utc>) -> Result<Option<(usize, DateTime<Utc>)>, TaskError> {
+        let completed = {
+            let mut task_entry = self.tasks.get_mut(&id).ok_or(TaskError::NotFound(id))?;
+            let task = task_entry.value_mut();
+            task.status = TaskStatus::Done;
+            task.completed_at = Some(now);
+            task.clone()
+        };
+        self.save_task_to_file(&completed).await?;
+
+        if completed.recur_from != RecurFrom::Completion {
+            return Ok(None);
+        }
+        let Some(next) = completed.next_instance(&self.calendar) else {
+            return Ok(None);
+        };
+        let start = next.start_time;
+        Ok(self.hand_over(id, Some(next)).await?.map(|next_id| (next_id, start)))
+    }
+
+    /// Pass the chain of a series from its head to the next occurrence, or end it without one.
+    /// Only the newest occurrence stays recurring, so catch-up and skip-next act on it.
+    pub async fn hand_over(&self, head_id: usize, next: Option<Task>) -> Result<Option<usize>, TaskError> {
+        if let Some(mut task_entry) = self.tasks.get_mut(&head_id) {
+            task_entry.value_mut().is_recurring = false;
+        }
+        match next {
+            Some(next) => Ok(Some(self.insert_task(next).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Regenerate recurring series whose chain stopped because their last occurrence ended
+    /// while nothing was running. Each series follows its own catch-up policy; the next
+    /// upcoming occurrence is always created so the series carries on.
+    pub async fn catch_up_recurrences(&self, now: DateTime<Utc>) -> Result<Vec<CatchUpReport>, TaskError> {
+        // The latest recurring occurrence of each series is the one that drives the chain
+        let mut heads: HashMap<usize, Task> = HashMap::new();
+        for entry in self.tasks.iter() {
+            let task = entry.value();
+            if !task.is_recurring {
+                continue;
             }
-            if let Some(frequency_minutes) = update.frequency_minutes {
-                task.frequency_minutes = Some(frequency_minutes);
+            // Completion-relative series only move on when an occurrence is done
+            if task.recur_from == RecurFrom::Completion {
+                continue;
             }
-            self.save_task_to_file(task).await?;
-            Ok(())
-        } else {
-            Err(TaskError::NotFound(id))
+            let is_latest = heads
+                .get(&task.series())
+                .is_none_or(|head| head.start_time < task.start_time);
+            if is_latest {
+                heads.insert(task.series(), task.clone());
+            }
+        }
+
+        let mut heads: Vec<Task> = heads.into_values().filter(|head| head.end_time <= now).collect();
+        heads.sort_by_key(|head| head.id);
+
+        let mut reports = Vec::new();
+        for head in heads {
+            let mut missed = Vec::new();
+            let mut upcoming = None;
+            let mut current = head.clone();
+            while let Some(next) = current.next_instance(&self.calendar) {
+                if next.end_time > now {
+                    upcoming = Some(next);
+                    break;
+                }
+                missed.push(next.clone());
+                current = next;
+            }
+
+            if missed.is_empty() && upcoming.is_none() {
+                continue;
+            }
+
+            // Past occurrences are kept as plain records; only the upcoming one continues the chain
+            for occurrence in &missed {
+                let record = Task {
+                    is_recurring: false,
+                    status: match head.catch_up {
+                        CatchUpPolicy::MarkMissed => TaskStatus::Missed,
+                        _ => TaskStatus::Pending,
+                    },
+                    ..occurrence.clone()
+                };
+                if head.catch_up != CatchUpPolicy::Next {
+                    self.insert_task(record).await?;
+                }
+            }
+
+            let start = upcoming.as_ref().map(|task| task.start_time);
+            let next = self.hand_over(head.id, upcoming).await?.zip(start);
+
+            reports.push(CatchUpReport {
+                series_id: head.series(),
+                title: head.title.clone(),
+                policy: head.catch_up,
+                missed: missed.iter().map(|task| task.start_time).collect(),
+                next,
+            });
         }
+        Ok(reports)
     }
 
-    pub async fn export_to_csv(&self, filename: &str) -> Result<(), TaskError> {
-        self.exportable.export_to_csv(filename).await
+    // Turn a recurring occurrence into a one-off exception of its series. The series
+    // carries on from the following occurrence, generated from the unedited task.
+    async fn detach_occurrence(&self, task: &Task) -> Result<(), TaskError> {
+        if let Some(next) = task.next_instance(&self.calendar) {
+            let exists = self
+                .tasks
+                .iter()
+                .any(|entry| entry.value().series() == task.series() && entry.value().start_time == next.start_time);
+            if !exists {
+                self.insert_task(next).await?;
+            }
+        }
+        if let Some(mut task_entry) = self.tasks.get_mut(&task.id) {
+            let occurrence = task_entry.value_mut();
+            occurrence.is_recurring = false;
+            occurrence.original_start.get_or_insert(task.start_time);
+        }
+        Ok(())
+    }
+
+    /// Cancel the next upcoming occurrence of a recurring task without ending the series.
+    /// If the stored occurrence hasn't started yet it is moved on to the following one,
+    /// otherwise the one after it is excluded. Returns the skipped start time.
+    pub async fn skip_next(&self, id: usize, now: DateTime<Utc>) -> Result<DateTime<Utc>, TaskError> {
+        let mut task_entry = self.tasks.get_mut(&id).ok_or(TaskError::NotFound(id))?;
+        let task = task_entry.value_mut();
+        if task.recurrence_rule().is_none() {
+            return Err(TaskError::NotRecurring(id));
+        }
+
+        let skipped = if task.start_time > now {
+            let following = task.next_instance(&self.calendar).ok_or(TaskError::NotRecurring(id))?;
+            let skipped = task.start_time;
+            // Exclusions refer to the slot the rule produced, before any non-working-day shift
+            task.exdates.push(task.original_start.unwrap_or(skipped));
+            task.start_time = following.start_time;
+            task.end_time = following.end_time;
+            task.occurrence_index = following.occurrence_index;
+            task.original_start = following.original_start;
+            skipped
+        } else {
+            let following = task.next_instance(&self.calendar).ok_or(TaskError::NotRecurring(id))?;
+            task.exdates.push(following.original_start.unwrap_or(following.start_time));
+            following.start_time
+        };
+
+        self.save_task_to_file(task).await?;
+        Ok(skipped)
     }
 
-    pub async fn export_to_json(&self, filename: &str) -> Result<(), TaskError> {
-        self.exportable.export_to_json(filename).await
+    pub async fn export_to_csv(&self, filename: &str, filter: Option<&Filter>) -> Result<(), TaskError> {
+        self.exportable.export_to_csv(filename, filter).await
     }
 
-    pub async fn export_to_pdf(&self, filename: &str) -> Result<(), TaskError> {
-        self.exportable.export_to_pdf(filename).await
+    pub async fn export_to_json(&self, filename: &str, filter: Option<&Filter>) -> Result<(), TaskError> {
+        self.exportable.export_to_json(filename, filter).await
+    }
+
+    pub async fn export_to_pdf(&self, filename: &str, filter: Option<&Filter>) -> Result<(), TaskError> {
+        self.exportable.export_to_pdf(filename, filter).await
     }
     pub async fn save_to_file(&self, folder_path: &str) -> Result<(), TaskError> {
         self.read_write.save_to_file(folder_path).await
     }
     pub async fn load_from_file(&self, folder_path: &str) -> Result<(), TaskError> {
-        self.read_write.load_from_file(folder_path).await
+        self.read_write.load_from_file(folder_path).await?;
+        let next_id = self.tasks.iter().map(|entry| *entry.key()).max().unwrap_or(0) + 1;
+        self.next_id.store(next_id, Ordering::SeqCst);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Zone;
+    use chrono::{FixedOffset, TimeZone};
+
+    // An in-memory state in UTC that never writes task files
+    fn state(conflict_policy: ConflictPolicy) -> AppState {
+        let calendar = WorkCalendar { zone: Zone::Fixed(FixedOffset::east_opt(0).unwrap()), ..WorkCalendar::default() };
+        let folder = std::env::temp_dir().join("todo_task_tests");
+        AppState::new(folder.to_string_lossy().into_owned(), calendar, conflict_policy).detached()
     }
-}
\ No newline at end of file
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn task(title: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        Task::new(title.into(), String::new(), start, end, false, None, None)
+    }
+
+    fn daily(title: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        Task::new(title.into(), String::new(), start, end, true, None, Some("FREQ=DAILY".parse().unwrap()))
+    }
+
+    // Start times of the occurrences a task's chain generates
+    fn chain(task: &Task, calendar: &WorkCalendar) -> Vec<DateTime<Utc>> {
+        std::iter::successors(task.next_instance(calendar), |task| task.next_instance(calendar))
+            .take(10)
+            .map(|task| task.start_time)
+            .collect()
+    }
+
+    #[test]
+    fn count_and_exdates_end_and_thin_a_series() {
+        let calendar = state(ConflictPolicy::Allow).calendar;
+        let mut task = daily("Standup", at(1, 9), at(1, 10));
+        task.recurrence = Some("FREQ=DAILY;COUNT=4".parse().unwrap());
+        assert_eq!(chain(&task, &calendar), [at(2, 9), at(3, 9), at(4, 9)]);
+
+        // excluded occurrences still count towards COUNT
+        task.exdates = vec![at(3, 9)];
+        assert_eq!(chain(&task, &calendar), [at(2, 9), at(4, 9)]);
+
+        task.recurrence = Some("FREQ=DAILY;UNTIL=20300103T090000Z".parse().unwrap());
+        task.exdates.clear();
+        assert_eq!(chain(&task, &calendar), [at(2, 9), at(3, 9)]);
+    }
+
+    #[tokio::test]
+    async fn skip_next_moves_an_unstarted_occurrence_on() {
+        let state = state(ConflictPolicy::Allow);
+        let id = state.add_task(daily("Standup", at(2, 9), at(2, 10))).await.unwrap();
+
+        assert_eq!(state.skip_next(id, at(1, 12)).await.unwrap(), at(2, 9));
+        let task = state.list_tasks_by_id(id).await.unwrap();
+        assert_eq!((task.start_time, task.end_time), (at(3, 9), at(3, 10)));
+        assert_eq!(task.exdates, [at(2, 9)]);
+
+        // once it has started, the one after it is excluded instead
+        assert_eq!(state.skip_next(id, at(3, 9)).await.unwrap(), at(4, 9));
+        assert_eq!(chain(&state.list_tasks_by_id(id).await.unwrap(), &state.calendar)[0], at(5, 9));
+        assert!(matches!(state.skip_next(999, at(3, 9)).await, Err(TaskError::NotFound(999))));
+    }
+
+    // A daily series whose first three occurrences are stored, the third one driving it
+    async fn standups(state: &AppState, rule: &str) -> Vec<usize> {
+        let mut head = daily("Standup", at(1, 9), at(1, 10));
+        head.recurrence = Some(rule.parse().unwrap());
+        let mut ids = vec![state.insert_task(head).await.unwrap()];
+        for _ in 0..2 {
+            let head = state.list_tasks_by_id(*ids.last().unwrap()).await.unwrap();
+            ids.push(state.hand_over(head.id, head.next_instance(&state.calendar)).await.unwrap().unwrap());
+        }
+        ids
+    }
+
+    fn titled(title: &str) -> TaskUpdate {
+        TaskUpdate { title: Some(title.into()), ..TaskUpdate::default() }
+    }
+
+    #[tokio::test]
+    async fn editing_one_occurrence_hands_the_series_on() {
+        let state = state(ConflictPolicy::Allow);
+        let ids = standups(&state, "FREQ=DAILY").await;
+
+        assert_eq!(state.edit_series(ids[2], titled("Retro"), EditScope::This).await.unwrap(), [ids[2]]);
+        let edited = state.list_tasks_by_id(ids[2]).await.unwrap();
+        assert_eq!((edited.title.as_str(), edited.is_recurring, edited.original_start), ("Retro", false, Some(at(3, 9))));
+
+        let head = state.tasks.iter().find(|entry| entry.is_recurring).map(|entry| entry.value().clone()).unwrap();
+        assert_eq!((head.title.as_str(), head.start_time, head.series()), ("Standup", at(4, 9), ids[0]));
+
+        // past occurrences are edited on their own
+        assert_eq!(state.edit_series(ids[0], titled("Kick-off"), EditScope::This).await.unwrap(), [ids[0]]);
+        assert_eq!(state.tasks.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn editing_the_whole_series_keeps_it_together() {
+        let state = state(ConflictPolicy::Allow);
+        let ids = standups(&state, "FREQ=DAILY;COUNT=5").await;
+        let update = TaskUpdate { start_time: Some(at(2, 8)), ..titled("Daily sync") };
+
+        assert_eq!(state.edit_series(ids[1], update, EditScope::Series).await.unwrap(), ids);
+        for (day, id) in (1..).zip(&ids) {
+            let task = state.list_tasks_by_id(*id).await.unwrap();
+            assert_eq!((task.title.as_str(), task.start_time, task.series()), ("Daily sync", at(day, 8), ids[0]));
+        }
+    }
+
+    #[tokio::test]
+    async fn editing_future_occurrences_splits_the_series() {
+        let state = state(ConflictPolicy::Allow);
+        let ids = standups(&state, "FREQ=DAILY;COUNT=5").await;
+        let update = TaskUpdate { start_time: Some(at(2, 10)), ..titled("Late standup") };
+
+        assert_eq!(state.edit_series(ids[1], update, EditScope::Future).await.unwrap(), [ids[1], ids[2]]);
+        let first = state.list_tasks_by_id(ids[0]).await.unwrap();
+        assert_eq!((first.title.as_str(), first.series()), ("Standup", ids[0]));
+        assert_eq!(first.recurrence.unwrap().count, Some(1));
+
+        let head = state.list_tasks_by_id(ids[2]).await.unwrap();
+        assert_eq!((head.title.as_str(), head.start_time, head.series(), head.is_recurring), ("Late standup", at(3, 10), ids[1], true));
+        // the new series carries on until the original COUNT runs out
+        assert_eq!(chain(&head, &state.calendar), [at(4, 10), at(5, 10)]);
+
+        // without COUNT the old series ends with an UNTIL just before the split
+        let state = self::state(ConflictPolicy::Allow);
+        let ids = standups(&state, "FREQ=DAILY").await;
+        state.edit_series(ids[2], titled("Late standup"), EditScope::Future).await.unwrap();
+        for id in &ids[..2] {
+            let rule = state.list_tasks_by_id(*id).await.unwrap().recurrence.unwrap();
+            assert_eq!(rule.until, Some(at(3, 9) - Duration::seconds(1)));
+        }
+        assert_eq!(state.list_tasks_by_id(ids[2]).await.unwrap().recurrence.unwrap().until, None);
+    }
+
+    #[tokio::test]
+    async fn completion_relative_series_recur_from_when_they_were_done() {
+        let state = state(ConflictPolicy::Allow);
+        let mut chore = daily("Water plants", at(1, 18), at(1, 19));
+        chore.recurrence = Some("FREQ=DAILY;INTERVAL=3".parse().unwrap());
+        chore.recur_from = RecurFrom::Completion;
+        let id = state.insert_task(chore).await.unwrap();
+        // nothing to create until it is done
+        assert!(state.list_tasks_by_id(id).await.unwrap().next_instance(&state.calendar).is_none());
+
+        // done the next morning: three days on from then, not from the 1st at 18:00
+        let done_at = at(2, 7) + Duration::minutes(30) + Duration::milliseconds(250);
+        let (next_id, start) = state.complete_task(id, done_at).await.unwrap().unwrap();
+        assert_eq!(start, at(5, 7) + Duration::minutes(30));
+
+        let done = state.list_tasks_by_id(id).await.unwrap();
+        assert_eq!((done.status, done.completed_at, done.is_recurring), (TaskStatus::Done, Some(done_at), false));
+        let next = state.list_tasks_by_id(next_id).await.unwrap();
+        assert_eq!((next.start_time, next.end_time, next.series()), (start, start + Duration::hours(1), id));
+        assert_eq!((next.status, next.completed_at, next.is_recurring), (TaskStatus::Pending, None, true));
+
+        // schedule-anchored series are not moved on by `done`
+        let standup = state.insert_task(daily("Standup", at(1, 9), at(1, 10))).await.unwrap();
+        assert_eq!(state.complete_task(standup, at(1, 12)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn catch_up_records_missed_occurrences_and_continues_the_series() {
+        let state = state(ConflictPolicy::Allow);
+        let mut standup = daily("Standup", at(1, 9), at(1, 10));
+        standup.catch_up = CatchUpPolicy::MarkMissed;
+        let head = state.add_task(standup).await.unwrap();
+
+        let reports = state.catch_up_recurrences(at(4, 8)).await.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].missed, [at(2, 9), at(3, 9)]);
+        let (next, start) = reports[0].next.unwrap();
+        assert_eq!(start, at(4, 9));
+
+        let mut tasks: Vec<Task> = state.tasks.iter().map(|entry| entry.value().clone()).collect();
+        tasks.sort_by_key(|task| task.start_time);
+        let statuses: Vec<(TaskStatus, bool)> = tasks.iter().map(|task| (task.status, task.is_recurring)).collect();
+        assert_eq!(
+            statuses,
+            [(TaskStatus::Pending, false), (TaskStatus::Missed, false), (TaskStatus::Missed, false), (TaskStatus::Pending, true)]
+        );
+        assert!(tasks.iter().all(|task| task.series() == head));
+        assert_eq!(tasks[3].id, next);
+
+        // nothing more to do until the new head ends
+        assert!(state.catch_up_recurrences(at(4, 9)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refuse_policy_rejects_overlap_with_an_upcoming_occurrence() {
+        let state = state(ConflictPolicy::Refuse);
+        let series = state.add_task(daily("Standup", at(1, 9), at(1, 10))).await.unwrap();
+
+        let err = state.add_task(task("Call", at(3, 9), at(3, 11))).await.unwrap_err();
+        assert!(matches!(err, TaskError::UpcomingConflict { series: id, start, .. } if id == series && start == at(3, 9)));
+        assert!(state.add_task(task("Lunch", at(3, 12), at(3, 13))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn moving_a_series_ignores_its_own_upcoming_occurrences() {
+        let state = state(ConflictPolicy::Refuse);
+        let series = state.add_task(daily("Standup", at(1, 9), at(1, 10))).await.unwrap();
+        let update = TaskUpdate { length: Some(Duration::hours(30)), ..TaskUpdate::default() };
+        assert!(state.edit_series(series, update, EditScope::Series).await.is_ok());
+    }
+}
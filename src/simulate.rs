@@ -0,0 +1,165 @@
+use crate::config::{Config, Zone};
+use crate::error::TaskError;
+use crate::notify::{Notification, Notifier};
+use crate::reminder_state::ReminderState;
+use crate::scheduler::{Event, EventKind, Scheduler};
+use crate::shared::AppState;
+
+use chrono::{DateTime, SubsecRound, Utc};
+use std::sync::Arc;
+
+/// Prints each notification with the simulated time it would be sent at
+struct TimelineNotifier {
+    zone: Zone,
+}
+
+impl Notifier for TimelineNotifier {
+    fn name(&self) -> &str {
+        "timeline"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+        println!(
+            "{}  {:<9} {}",
+            self.zone.to_local(notification.at).format("%Y-%m-%d %H:%M:%S"),
+            notification.event,
+            notification.message
+        );
+        Ok(())
+    }
+}
+
+/// Runs the scheduler from `from` to `until` without waiting and without touching the store,
+/// printing every reminder, overdue alert and recurrence the daemon would produce
+pub async fn run(
+    state: &AppState,
+    config: Config,
+    reminder_state: ReminderState,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<(), TaskError> {
+    if until <= from {
+        return Err(TaskError::InvalidTime(format!("--until {} must be after {}", until, from.trunc_subsecs(0))));
+    }
+    println!("Simulating from {} to {}", from.trunc_subsecs(0), until);
+    let notifiers: Vec<Arc<dyn Notifier>> = vec![Arc::new(TimelineNotifier { zone: state.calendar.zone })];
+    let (events, created) = replay(state, config, reminder_state, notifiers, from, until).await?;
+
+    let (mut reminders, mut overdue) = (0, 0);
+    for event in events {
+        match event.kind {
+            EventKind::Reminder(_) | EventKind::HeldReminder(_) => reminders += 1,
+            EventKind::Overdue(_) => overdue += 1,
+            EventKind::End => {}
+        }
+    }
+    println!(
+        "Simulation finished: {} reminder(s), {} overdue alert(s), {} new occurrence(s).",
+        reminders, overdue, created
+    );
+    Ok(())
+}
+
+// Fires every event due up to `until` on a copy of the state, in order, and returns them
+// with the number of occurrences the series created
+async fn replay(
+    state: &AppState,
+    config: Config,
+    reminder_state: ReminderState,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<(Vec<Event>, usize), TaskError> {
+    let state = Arc::new(state.detached());
+    for report in state.catch_up_recurrences(from).await? {
+        println!("{}", report);
+    }
+    let tasks_before = state.tasks.len();
+
+    let mut scheduler = Scheduler::new(Arc::clone(&state), config, notifiers);
+    scheduler.set_reminder_state(reminder_state);
+    scheduler.sync(from);
+
+    let mut events = Vec::new();
+    while let Some(at) = scheduler.next_fire_time() {
+        if at > until {
+            break;
+        }
+        events.extend(scheduler.run_due(at).await?);
+    }
+    Ok((events, state.tasks.len() - tasks_before))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflicts::ConflictPolicy;
+    use crate::holidays::WorkCalendar;
+    use crate::overdue::Escalation;
+    use crate::shared::Task;
+    use chrono::{FixedOffset, TimeZone};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, DateTime<Utc>, String)>>);
+
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn notify(&self, notification: &Notification) -> Result<(), TaskError> {
+            let sent = (notification.event.clone(), notification.at, notification.message.clone());
+            self.0.lock().unwrap().push(sent);
+            Ok(())
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_reminders_overdue_alerts_and_recurrences_in_order() {
+        let calendar = WorkCalendar { zone: Zone::Fixed(FixedOffset::east_opt(0).unwrap()), ..WorkCalendar::default() };
+        let folder = std::env::temp_dir().join("todo_task_tests");
+        let state = AppState::new(folder.to_string_lossy().into_owned(), calendar, ConflictPolicy::Allow).detached();
+        let report = state.insert_task(Task::new("Report".into(), String::new(), at(9, 0), at(10, 0), false, None, None)).await.unwrap();
+        let standup = Task::new("Standup".into(), String::new(), at(11, 0), at(11, 15), true, None, Some("FREQ=DAILY".parse().unwrap()));
+        let standup = state.insert_task(standup).await.unwrap();
+
+        let config = Config {
+            default_reminders: vec!["start-5m".parse().unwrap()],
+            escalation: Escalation { after: Vec::new(), bump_priority: false },
+            ..Config::default()
+        };
+        let recorder = Arc::new(Recorder::default());
+        let notifiers: Vec<Arc<dyn Notifier>> = vec![Arc::clone(&recorder) as Arc<dyn Notifier>];
+        let (events, created) = replay(&state, config, ReminderState::default(), notifiers, at(8, 0), at(12, 0)).await.unwrap();
+
+        let fired: Vec<(DateTime<Utc>, usize, EventKind)> = events.iter().map(|event| (event.at, event.task_id, event.kind)).collect();
+        assert_eq!(
+            fired,
+            [
+                (at(8, 55), report, EventKind::Reminder(0)),
+                (at(10, 0), report, EventKind::Overdue(0)),
+                (at(10, 55), standup, EventKind::Reminder(0)),
+                (at(11, 15), standup, EventKind::End),
+                (at(11, 15), standup, EventKind::Overdue(0)),
+            ]
+        );
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                ("reminder".to_string(), at(8, 55), "Reminder: 'Report' starts in 5 minutes!".to_string()),
+                ("overdue".to_string(), at(10, 0), "Task 'Report' is overdue".to_string()),
+                ("reminder".to_string(), at(10, 55), "Reminder: 'Standup' starts in 5 minutes!".to_string()),
+                ("overdue".to_string(), at(11, 15), "Task 'Standup' is overdue".to_string()),
+            ]
+        );
+        // the next standup was created on the copy only
+        assert_eq!(created, 1);
+        assert_eq!(state.tasks.len(), 2);
+        assert!(state.tasks.get(&standup).unwrap().is_recurring);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::agenda;
+use crate::config::Zone;
+use crate::error::TaskError;
+use crate::holidays::WorkCalendar;
+use crate::shared::{Task, TaskStatus};
+
+use chrono::{DateTime, Duration, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+// Searches start at the next quarter hour rather than at the current second
+const SLOT_STEP_MINUTES: i64 = 15;
+
+/// Part of each working day that tasks may be placed in, as local wall-clock times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        WorkingHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        }
+    }
+}
+
+impl WorkingHours {
+    pub fn validate(&self) -> Result<(), TaskError> {
+        if self.end <= self.start {
+            return Err(TaskError::Config(format!(
+                "working hours end {} must be after their start {}",
+                self.end, self.start
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How far ahead to look for a free slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Within {
+    /// The rest of today
+    Today,
+    /// Tomorrow
+    Tomorrow,
+    /// The next 7 days
+    Week,
+    /// The next 30 days
+    Month,
+}
+
+impl Within {
+    pub fn range(self, now: DateTime<Utc>, zone: Zone) -> (DateTime<Utc>, DateTime<Utc>) {
+        let midnight = |days: i64| {
+            let date = zone.to_local(now).date() + Duration::days(days);
+            zone.to_utc(date.and_time(NaiveTime::MIN))
+        };
+        match self {
+            Within::Today => (now, midnight(1)),
+            Within::Tomorrow => (midnight(1), midnight(2)),
+            Within::Week => (now, now + Duration::days(7)),
+            Within::Month => (now, now + Duration::days(30)),
+        }
+    }
+}
+
+/// Gaps of at least `duration` between pending tasks, including occurrences recurring series
+/// will create, during working hours on working days between `from` and `to`, earliest first
+pub fn free_slots(
+    tasks: &[Task],
+    calendar: &WorkCalendar,
+    hours: &WorkingHours,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    duration: Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let from = round_up(from);
+    let mut busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = agenda::occurrences(tasks, calendar, from, to)
+        .into_iter()
+        .filter(|occurrence| occurrence.task.status == TaskStatus::Pending)
+        .map(|occurrence| (occurrence.task.start_time, occurrence.task.end_time))
+        .collect();
+    busy.sort();
+
+    let mut slots = Vec::new();
+    let mut day = calendar.zone.to_local(from).date();
+    let last = calendar.zone.to_local(to).date();
+    while day <= last {
+        if calendar.is_working_day(day) {
+            let open = calendar.zone.to_utc(day.and_time(hours.start)).max(from);
+            let close = calendar.zone.to_utc(day.and_time(hours.end)).min(to);
+            let mut cursor = open;
+            for (start, end) in &busy {
+                if *start >= close {
+                    break;
+                }
+                if *end <= cursor {
+                    continue;
+                }
+                if *start - cursor >= duration {
+                    slots.push((cursor, *start));
+                }
+                cursor = cursor.max(*end);
+            }
+            if close - cursor >= duration {
+                slots.push((cursor, close));
+            }
+        }
+        let Some(next) = day.succ_opt() else { break };
+        day = next;
+    }
+    slots
+}
+
+fn round_up(time: DateTime<Utc>) -> DateTime<Utc> {
+    let step = SLOT_STEP_MINUTES * 60;
+    let seconds = i64::from(time.num_seconds_from_midnight());
+    let extra = (step - seconds % step) % step;
+    let rounded = time.with_nanosecond(0).unwrap_or(time) + Duration::seconds(extra);
+    if rounded < time {
+        rounded + Duration::seconds(step)
+    } else {
+        rounded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn calendar() -> WorkCalendar {
+        WorkCalendar { zone: Zone::Fixed(FixedOffset::east_opt(0).unwrap()), ..WorkCalendar::default() }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn task(id: usize, start: DateTime<Utc>, end: DateTime<Utc>) -> Task {
+        let mut task = Task::new(format!("Task {}", id), String::new(), start, end, false, None, None);
+        task.id = id;
+        task
+    }
+
+    #[test]
+    fn gaps_between_tasks_within_working_hours() {
+        // Tuesday 2030-01-01
+        let tasks = [task(1, at(1, 10, 0), at(1, 11, 0)), task(2, at(1, 10, 30), at(1, 12, 0)), task(3, at(1, 16, 0), at(1, 18, 0))];
+        let slots = free_slots(&tasks, &calendar(), &WorkingHours::default(), at(1, 8, 0), at(2, 0, 0), Duration::hours(1));
+        assert_eq!(slots, [(at(1, 9, 0), at(1, 10, 0)), (at(1, 12, 0), at(1, 16, 0))]);
+    }
+
+    #[test]
+    fn search_starts_at_the_next_quarter_hour_and_skips_weekends() {
+        // Friday 2030-01-04 afternoon through Monday
+        let slots = free_slots(&[], &calendar(), &WorkingHours::default(), at(4, 16, 20), at(8, 0, 0), Duration::minutes(30));
+        assert_eq!(slots, [(at(4, 16, 30), at(4, 17, 0)), (at(7, 9, 0), at(7, 17, 0))]);
+    }
+
+    #[test]
+    fn done_tasks_leave_their_time_free() {
+        let mut done = task(1, at(1, 9, 0), at(1, 17, 0));
+        done.status = TaskStatus::Done;
+        let slots = free_slots(&[done], &calendar(), &WorkingHours::default(), at(1, 0, 0), at(2, 0, 0), Duration::hours(8));
+        assert_eq!(slots, [(at(1, 9, 0), at(1, 17, 0))]);
+    }
+
+    #[test]
+    fn projected_recurring_occurrences_are_busy() {
+        let mut daily = task(1, at(1, 9, 0), at(1, 17, 0));
+        daily.is_recurring = true;
+        daily.series_id = Some(1);
+        daily.recurrence = Some("FREQ=DAILY".parse().unwrap());
+        let slots = free_slots(&[daily], &calendar(), &WorkingHours::default(), at(2, 0, 0), at(4, 0, 0), Duration::minutes(15));
+        assert!(slots.is_empty(), "{:?}", slots);
+    }
+}
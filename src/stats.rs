@@ -0,0 +1,271 @@
+use crate::config::Zone;
+use crate::error::TaskError;
+use crate::output::{self, OutputFormat, Record, Value};
+use crate::shared::{Task, TaskStatus};
+use crate::time_parse::format_duration;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use std::collections::HashMap;
+
+// Categories listed in the time-per-category table
+const TOP_CATEGORIES: usize = 10;
+
+#[derive(Debug)]
+pub struct WeekStats {
+    /// ISO week, e.g. "2025-W03"
+    pub week: String,
+    /// Monday the week starts on
+    pub start: NaiveDate,
+    pub created: usize,
+    pub completed: usize,
+}
+
+#[derive(Debug)]
+pub struct TimeStats {
+    pub name: String,
+    pub tasks: usize,
+    pub minutes: i64,
+}
+
+/// Figures over the tasks of the last few weeks
+#[derive(Debug)]
+pub struct Stats {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub weeks: Vec<WeekStats>,
+    /// Tasks marked done within the period
+    pub completed: usize,
+    /// Of those, the ones done by their due time
+    pub on_time: usize,
+    pub on_time_rate: Option<f64>,
+    /// How late the late ones were on average
+    pub average_lateness_minutes: Option<i64>,
+    /// Scheduled time per category (task title), most first
+    pub categories: Vec<TimeStats>,
+    /// Scheduled time per weekday, Monday first
+    pub weekdays: Vec<TimeStats>,
+}
+
+/// Statistics for the `weeks` calendar weeks up to and including the current one.
+/// Creation is only known for tasks added since `created_at` was recorded.
+pub fn compute(tasks: &[Task], weeks: u32, now: DateTime<Utc>, zone: Zone) -> Stats {
+    let today = zone.to_local(now).date();
+    let this_monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+    let first_monday = this_monday - Duration::weeks(i64::from(weeks.max(1)) - 1);
+    let midnight = |date: NaiveDate| zone.to_utc(date.and_time(NaiveTime::MIN));
+    let from = midnight(first_monday);
+    let to = midnight(this_monday + Duration::weeks(1));
+    let week_of = |time: DateTime<Utc>| {
+        let date = zone.to_local(time).date();
+        (date - first_monday).num_days().div_euclid(7)
+    };
+
+    let mut week_stats: Vec<WeekStats> = first_monday
+        .iter_weeks()
+        .take_while(|monday| *monday <= this_monday)
+        .map(|monday| WeekStats {
+            week: format!("{}-W{:02}", monday.iso_week().year(), monday.iso_week().week()),
+            start: monday,
+            created: 0,
+            completed: 0,
+        })
+        .collect();
+    let in_period = |time: DateTime<Utc>| from <= time && time < to;
+
+    let mut completed = 0;
+    let mut on_time = 0;
+    let mut lateness = Duration::zero();
+    let mut categories: HashMap<&str, (usize, Duration)> = HashMap::new();
+    let mut weekdays: Vec<(usize, Duration)> = vec![(0, Duration::zero()); 7];
+    for task in tasks {
+        if let Some(created) = task.created_at.filter(|at| in_period(*at)) {
+            week_stats[week_of(created) as usize].created += 1;
+        }
+        if let Some(done) = task.completed_at.filter(|at| in_period(*at) && task.status == TaskStatus::Done) {
+            week_stats[week_of(done) as usize].completed += 1;
+            completed += 1;
+            if done <= task.due() {
+                on_time += 1;
+            } else {
+                lateness += done - task.due();
+            }
+        }
+        if in_period(task.start_time) {
+            let length = task.end_time - task.start_time;
+            let category = categories.entry(task.title.as_str()).or_insert((0, Duration::zero()));
+            category.0 += 1;
+            category.1 += length;
+            let weekday = &mut weekdays[zone.to_local(task.start_time).weekday().num_days_from_monday() as usize];
+            weekday.0 += 1;
+            weekday.1 += length;
+        }
+    }
+
+    let mut categories: Vec<TimeStats> = categories
+        .into_iter()
+        .map(|(name, (tasks, time))| TimeStats { name: name.to_string(), tasks, minutes: time.num_minutes() })
+        .collect();
+    categories.sort_by(|a, b| b.minutes.cmp(&a.minutes).then_with(|| a.name.cmp(&b.name)));
+    let late = completed - on_time;
+    Stats {
+        from,
+        to,
+        weeks: week_stats,
+        completed,
+        on_time,
+        on_time_rate: (completed > 0).then(|| on_time as f64 / completed as f64),
+        average_lateness_minutes: (late > 0).then(|| lateness.num_minutes() / late as i64),
+        categories,
+        weekdays: weekdays
+            .into_iter()
+            .enumerate()
+            .map(|(index, (tasks, time))| TimeStats {
+                name: Weekday::try_from(index as u8).map(|day| day.to_string()).unwrap_or_default(),
+                tasks,
+                minutes: time.num_minutes(),
+            })
+            .collect(),
+    }
+}
+
+/// Prints the report: one table per section, or one record per figure in the other formats
+pub fn print(stats: &Stats, format: OutputFormat, zone: Zone) -> Result<(), TaskError> {
+    match format {
+        OutputFormat::Table => print_tables(stats, zone),
+        format => output::print(format, zone, &records(stats), ""),
+    }
+}
+
+/// The report as one record per figure, e.g. section "week", name "2025-W03", metric "created"
+pub fn records(stats: &Stats) -> Vec<Record> {
+    let row = |section: &str, name: &str, metric: &'static str, value: Value| {
+        Record::new()
+            .field("section", Value::Text(section.to_string()))
+            .field("name", Value::Text(name.to_string()))
+            .field("metric", Value::Text(metric.to_string()))
+            .field("value", value)
+    };
+    let count = |count: usize| Value::Int(count as i64);
+    let mut records = vec![
+        row("period", "", "from", Value::Time(stats.from)),
+        row("period", "", "to", Value::Time(stats.to)),
+        row("completion", "", "completed", count(stats.completed)),
+        row("completion", "", "on_time", count(stats.on_time)),
+        row("completion", "", "on_time_rate", stats.on_time_rate.map_or(Value::Null, Value::Float)),
+        row("completion", "", "average_lateness_minutes", stats.average_lateness_minutes.map_or(Value::Null, Value::Int)),
+    ];
+    for week in &stats.weeks {
+        records.push(row("week", &week.week, "created", count(week.created)));
+        records.push(row("week", &week.week, "completed", count(week.completed)));
+    }
+    for (section, rows) in [("category", &stats.categories), ("weekday", &stats.weekdays)] {
+        for time in rows {
+            records.push(row(section, &time.name, "tasks", count(time.tasks)));
+            records.push(row(section, &time.name, "minutes", Value::Int(time.minutes)));
+        }
+    }
+    records
+}
+
+fn print_tables(stats: &Stats, zone: Zone) -> Result<(), TaskError> {
+    let section = |title: &str| println!("{}", output::paint(title, output::BOLD, output::use_colour()));
+    let minutes = |minutes: i64| Value::Text(format_duration(Duration::minutes(minutes)));
+
+    section("Completion");
+    let rate = stats.on_time_rate.map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0));
+    let lateness = stats.average_lateness_minutes.map_or("-".to_string(), |late| format_duration(Duration::minutes(late)));
+    println!("  Completed:          {}", stats.completed);
+    println!("  On time:            {} ({})", stats.on_time, rate);
+    println!("  Average lateness:   {}", lateness);
+
+    println!();
+    section("Per week");
+    let weeks: Vec<Record> = stats
+        .weeks
+        .iter()
+        .map(|week| {
+            Record::new()
+                .field("week", Value::Text(week.week.clone()))
+                .field("starts", Value::Text(week.start.to_string()))
+                .field("created", Value::Int(week.created as i64))
+                .field("completed", Value::Int(week.completed as i64))
+        })
+        .collect();
+    output::print(OutputFormat::Table, zone, &weeks, "")?;
+
+    println!();
+    section("Time per category");
+    let categories: Vec<Record> = stats
+        .categories
+        .iter()
+        .take(TOP_CATEGORIES)
+        .map(|category| {
+            Record::new()
+                .field("title", Value::Text(category.name.clone()))
+                .field("tasks", Value::Int(category.tasks as i64))
+                .field("time", minutes(category.minutes))
+        })
+        .collect();
+    output::print(OutputFormat::Table, zone, &categories, "  Nothing scheduled.")?;
+
+    println!();
+    section("Busiest weekdays");
+    let mut weekdays: Vec<&TimeStats> = stats.weekdays.iter().collect();
+    weekdays.sort_by(|a, b| b.minutes.cmp(&a.minutes).then(b.tasks.cmp(&a.tasks)));
+    let weekdays: Vec<Record> = weekdays
+        .into_iter()
+        .map(|weekday| {
+            Record::new()
+                .field("weekday", Value::Text(weekday.name.clone()))
+                .field("tasks", Value::Int(weekday.tasks as i64))
+                .field("time", minutes(weekday.minutes))
+        })
+        .collect();
+    output::print(OutputFormat::Table, zone, &weekdays, "")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn done(title: &str, start: DateTime<Utc>, completed: DateTime<Utc>) -> Task {
+        let mut task = Task::new(title.into(), String::new(), start, start + Duration::hours(1), false, None, None);
+        task.status = TaskStatus::Done;
+        task.completed_at = Some(completed);
+        task.created_at = Some(start - Duration::days(1));
+        task
+    }
+
+    #[test]
+    fn punctuality_and_time_per_category() {
+        // Wednesday 2030-01-09 in the second of two weeks
+        let now = at(9, 12);
+        let zone = Zone::Fixed(FixedOffset::east_opt(0).unwrap());
+        let tasks = [done("Review", at(2, 9), at(2, 10)), done("Review", at(8, 9), at(8, 12)), done("Call", at(9, 9), at(9, 9))];
+        let stats = compute(&tasks, 2, now, zone);
+
+        assert_eq!((stats.from, stats.to), (Utc.with_ymd_and_hms(2029, 12, 31, 0, 0, 0).unwrap(), at(14, 0)));
+        let weeks: Vec<(usize, usize)> = stats.weeks.iter().map(|week| (week.created, week.completed)).collect();
+        assert_eq!(weeks, [(1, 1), (2, 2)]);
+        assert_eq!((stats.completed, stats.on_time), (3, 2));
+        assert_eq!(stats.average_lateness_minutes, Some(120));
+        assert_eq!(stats.categories[0].name, "Review");
+        assert_eq!(stats.categories[0].minutes, 120);
+        assert_eq!(stats.weekdays[2].tasks, 2);
+    }
+
+    #[test]
+    fn records_hold_one_figure_each() {
+        let zone = Zone::Fixed(FixedOffset::east_opt(0).unwrap());
+        let stats = compute(&[], 1, at(9, 12), zone);
+        let records = records(&stats);
+        // period, completion, one week and seven weekdays
+        assert_eq!(records.len(), 2 + 4 + 2 + 7 * 2);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::config::Zone;
+use crate::error::TaskError;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, SubsecRound, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// A duration written like "90m" or "1d" in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CompactDuration(pub Duration);
+
+impl TryFrom<String> for CompactDuration {
+    type Error = TaskError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_duration(&value).map(CompactDuration)
+    }
+}
+
+impl From<CompactDuration> for String {
+    fn from(duration: CompactDuration) -> Self {
+        format_duration(duration.0)
+    }
+}
+
+/// Parse a compact duration such as "90m", "1h30m", "2d" or "45s", or one spelled out
+/// like "45 minutes" or "1 hour 30 minutes".
+/// Units: s, m, h, d, w; a bare number means minutes.
+pub fn parse_duration(input: &str) -> Result<Duration, TaskError> {
+    let input = input.trim();
+    let invalid = || TaskError::InvalidTime(format!("invalid duration '{}', expected e.g. 90m, 1h30m or 2d", input));
+    if input.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(minutes) = input.parse::<i64>() {
+        return Duration::try_minutes(minutes).filter(|_| minutes >= 0).ok_or_else(invalid);
+    }
+    let compact = compact_units(input).ok_or_else(invalid)?;
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in compact.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        let part = match c.to_ascii_lowercase() {
+            's' => Duration::try_seconds(value),
+            'm' => Duration::try_minutes(value),
+            'h' => Duration::try_hours(value),
+            'd' => Duration::try_days(value),
+            'w' => Duration::try_weeks(value),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        total = total.checked_add(&part).ok_or_else(invalid)?;
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Like `parse_duration`, for lengths of time that must be longer than zero, e.g. `--for`
+pub fn parse_length(input: &str) -> Result<Duration, TaskError> {
+    let length = parse_duration(input)?;
+    if length <= Duration::zero() {
+        return Err(TaskError::InvalidTime(format!("duration '{}' must be longer than zero", input.trim())));
+    }
+    Ok(length)
+}
+
+// "1 hour 30 minutes" -> "1h30m"; `None` for words that aren't units
+fn compact_units(input: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| -> Option<()> {
+        if !word.is_empty() {
+            let unit = match word.to_lowercase().as_str() {
+                "s" | "sec" | "secs" | "second" | "seconds" => 's',
+                "m" | "min" | "mins" | "minute" | "minutes" => 'm',
+                "h" | "hr" | "hrs" | "hour" | "hours" => 'h',
+                "d" | "day" | "days" => 'd',
+                "w" | "wk" | "wks" | "week" | "weeks" => 'w',
+                _ => return None,
+            };
+            out.push(unit);
+            word.clear();
+        }
+        Some(())
+    };
+    for c in input.chars() {
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out)?;
+            if !c.is_whitespace() {
+                out.push(c);
+            }
+        }
+    }
+    flush(&mut word, &mut out)?;
+    Some(out)
+}
+
+/// Resolve a point in time against the clock and timezone. Accepts
+/// - RFC 3339 ("2025-03-01T09:00:00Z") or a local date and time ("2025-03-01 09:00")
+/// - "now", offsets from now ("+2h", "-30m", "in 30 minutes")
+/// - a day ("today", "tomorrow", "yesterday", "friday", "next monday", "2025-03-01"),
+///   optionally followed by a time ("3pm", "at 9:30am", "15:00", "noon")
+/// - a time on its own, meaning the next time the clock shows it
+///
+/// A weekday is the coming one, today included; "next" skips today. A day without a time
+/// means its start.
+pub fn parse_when(input: &str, now: DateTime<Utc>, zone: Zone) -> Result<DateTime<Utc>, TaskError> {
+    let text = input.trim();
+    let invalid = || {
+        TaskError::InvalidTime(format!(
+            "invalid time '{}', expected e.g. 2025-03-01T09:00:00Z, tomorrow 3pm, next monday 09:00, +2h or in 30 minutes",
+            text
+        ))
+    };
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let lower = text.to_lowercase();
+    // Offsets count from the current second, so results print cleanly
+    let now = now.trunc_subsecs(0);
+    if lower == "now" {
+        return Ok(now);
+    }
+    if let Some(offset) = lower.strip_prefix('+').or_else(|| lower.strip_prefix("in ")) {
+        return parse_duration(offset).map(|offset| now + offset).map_err(|_| invalid());
+    }
+    if let Some(offset) = lower.strip_prefix('-') {
+        return parse_duration(offset).map(|offset| now - offset).map_err(|_| invalid());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(zone.to_utc(local));
+        }
+    }
+
+    let today = zone.to_local(now).date();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let (date, rest) = match parse_day(&words, today) {
+        Some((date, used)) => (Some(date), &words[used..]),
+        None => (None, &words[..]),
+    };
+    let rest = rest.strip_prefix(&["at"]).unwrap_or(rest);
+    let time = if rest.is_empty() { None } else { Some(parse_clock(&rest.concat()).ok_or_else(invalid)?) };
+
+    match (date, time) {
+        (Some(date), time) => Ok(zone.to_utc(date.and_time(time.unwrap_or(NaiveTime::MIN)))),
+        (None, Some(time)) => {
+            let at = zone.to_utc(today.and_time(time));
+            Ok(if at > now { at } else { zone.to_utc((today + Duration::days(1)).and_time(time)) })
+        }
+        (None, None) => Err(invalid()),
+    }
+}
+
+// The day the leading words name, and how many words that took
+fn parse_day(words: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let coming = |weekday: Weekday, skip_today: bool| {
+        let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        today + Duration::days(if ahead == 0 && skip_today { 7 } else { ahead.into() })
+    };
+    match words {
+        ["today", ..] => Some((today, 1)),
+        ["tomorrow", ..] => Some((today + Duration::days(1), 1)),
+        ["yesterday", ..] => Some((today - Duration::days(1), 1)),
+        ["next", day, ..] => day.parse::<Weekday>().ok().map(|weekday| (coming(weekday, true), 2)),
+        ["this", day, ..] => day.parse::<Weekday>().ok().map(|weekday| (coming(weekday, false), 2)),
+        [day, ..] => day
+            .parse::<Weekday>()
+            .ok()
+            .map(|weekday| (coming(weekday, false), 1))
+            .or_else(|| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok().map(|date| (date, 1))),
+        [] => None,
+    }
+}
+
+// "15:00", "9:30am", "3pm", "noon" or "midnight"
+fn parse_clock(input: &str) -> Option<NaiveTime> {
+    match input {
+        "noon" | "midday" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+    let (clock, afternoon) = match (input.strip_suffix("am"), input.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(false)),
+        (_, Some(clock)) => (clock, Some(true)),
+        _ => (input, None),
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None if afternoon.is_some() => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+    let hour = match afternoon {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Inverse of `parse_duration`, e.g. "1h30m"
+pub fn format_duration(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let mut seconds = duration.num_seconds().abs();
+    if seconds == 0 {
+        return "0m".to_string();
+    }
+    let mut out = sign.to_string();
+    for (unit, size) in [("w", 604_800), ("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+        if seconds >= size {
+            out.push_str(&format!("{}{}", seconds / size, unit));
+            seconds %= size;
+        }
+    }
+    out
+}
+
+/// Duration in words for messages, e.g. "5 minutes" or "1 hour 30 minutes"
+pub fn humanize_duration(duration: Duration) -> String {
+    let mut seconds = duration.num_seconds().abs();
+    if seconds < 60 {
+        return plural(seconds, "second");
+    }
+    let mut parts = Vec::new();
+    for (unit, size) in [("week", 604_800), ("day", 86_400), ("hour", 3_600), ("minute", 60)] {
+        if seconds >= size {
+            parts.push(plural(seconds / size, unit));
+            seconds %= size;
+        }
+    }
+    parts.join(" ")
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    // Tuesday 2030-01-01 09:00 local, an hour ahead of UTC
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, 8, 0, 0).unwrap()
+    }
+
+    fn zone() -> Zone {
+        Zone::Fixed(FixedOffset::east_opt(3600).unwrap())
+    }
+
+    // Local wall-clock time on a January 2030 day
+    fn local(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, hour, minute, 0).unwrap() - Duration::hours(1)
+    }
+
+    fn when(input: &str) -> DateTime<Utc> {
+        parse_when(input, now(), zone()).unwrap_or_else(|err| panic!("{}: {}", input, err))
+    }
+
+    #[test]
+    fn absolute_times() {
+        assert_eq!(when("2030-02-01T10:00:00Z"), Utc.with_ymd_and_hms(2030, 2, 1, 10, 0, 0).unwrap());
+        assert_eq!(when("2030-01-05 14:30"), local(5, 14, 30));
+        assert_eq!(when("2030-01-05T14:30"), local(5, 14, 30));
+        assert_eq!(when("2030-01-05"), local(5, 0, 0));
+    }
+
+    #[test]
+    fn relative_times() {
+        assert_eq!(when("now"), now());
+        assert_eq!(when("+2h"), now() + Duration::hours(2));
+        assert_eq!(when("-30m"), now() - Duration::minutes(30));
+        assert_eq!(when("in 1 hour 30 minutes"), now() + Duration::minutes(90));
+        // sub-second parts of the clock are dropped
+        assert_eq!(parse_when("+1m", now() + Duration::milliseconds(250), zone()).unwrap(), now() + Duration::minutes(1));
+    }
+
+    #[test]
+    fn named_days() {
+        assert_eq!(when("today"), local(1, 0, 0));
+        assert_eq!(when("tomorrow 3pm"), local(2, 15, 0));
+        assert_eq!(when("yesterday at noon"), Utc.with_ymd_and_hms(2029, 12, 31, 11, 0, 0).unwrap());
+        assert_eq!(when("friday 9:30am"), local(4, 9, 30));
+        assert_eq!(when("tuesday 17:00"), local(1, 17, 0));
+        assert_eq!(when("this tuesday"), local(1, 0, 0));
+        assert_eq!(when("next tuesday"), local(8, 0, 0));
+        assert_eq!(when("next monday 09:00"), local(7, 9, 0));
+        assert_eq!(when("2030-01-10 at midnight"), local(10, 0, 0));
+    }
+
+    #[test]
+    fn bare_times_mean_their_next_occurrence() {
+        assert_eq!(when("3pm"), local(1, 15, 0));
+        assert_eq!(when("8am"), local(2, 8, 0));
+        assert_eq!(when("12am"), local(2, 0, 0));
+    }
+
+    #[test]
+    fn nonsense_is_rejected() {
+        for input in ["", "someday", "tomorrow 25pm", "13pm", "next week", "9:5", "+", "in soon"] {
+            assert!(parse_when(input, now(), zone()).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("1 hour 30 minutes").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::days(2));
+        assert_eq!(parse_duration("45s").unwrap(), Duration::seconds(45));
+        assert_eq!(parse_duration("0m").unwrap(), Duration::zero());
+        for input in ["", "-5", "1x", "h", "1h30", "1 fortnight"] {
+            assert!(parse_duration(input).is_err(), "{}", input);
+        }
+        assert_eq!(format_duration(Duration::minutes(90)), "1h30m");
+    }
+
+    #[test]
+    fn lengths_must_be_positive() {
+        assert_eq!(parse_length("45m").unwrap(), Duration::minutes(45));
+        assert!(parse_length("0m").is_err());
+        assert!(parse_length("0").is_err());
+        assert!(parse_length("-10").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::config::Zone;
+use crate::error::TaskError;
+use crate::filter::Filter;
+use crate::output::{OutputFormat, Record, Value};
+use crate::query::{SortKey, TaskQuery};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Words `view` takes as subcommands, so they can't name a view
+const RESERVED_NAMES: [&str; 3] = ["save", "list", "delete"];
+
+/// A named listing kept in the config, e.g. { "filter": "tag:ops and start<+7d", "sort": "priority" }
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct View {
+    /// Filter expression, parsed each time the view runs so relative times stay current
+    pub filter: Option<String>,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub limit: Option<usize>,
+    pub output: OutputFormat,
+}
+
+impl View {
+    pub fn validate(&self, name: &str, now: DateTime<Utc>, zone: Zone) -> Result<(), TaskError> {
+        if name.trim().is_empty() || RESERVED_NAMES.contains(&name) {
+            return Err(TaskError::Config(format!("'{}' can't be used as a view name", name)));
+        }
+        self.parse_filter(now, zone).map(|_| ())
+    }
+
+    pub fn parse_filter(&self, now: DateTime<Utc>, zone: Zone) -> Result<Option<Filter>, TaskError> {
+        self.filter.as_deref().map(|expr| Filter::parse(expr, now, zone)).transpose()
+    }
+
+    /// How `view list` shows the view
+    pub fn record(&self, name: &str) -> Record {
+        Record::new()
+            .field("name", Value::Text(name.to_string()))
+            .field("sort", Value::Text(format!("{:?}", self.sort).to_lowercase()))
+            .field("reverse", Value::Bool(self.reverse))
+            .field("limit", self.limit.map_or(Value::Null, |limit| Value::Int(limit as i64)))
+            .field("output", Value::Text(format!("{:?}", self.output).to_lowercase()))
+            .field("filter", Value::Text(self.filter.clone().unwrap_or_default()))
+    }
+
+    pub fn query(&self, now: DateTime<Utc>, zone: Zone) -> Result<TaskQuery, TaskError> {
+        Ok(TaskQuery {
+            sort: self.sort,
+            reverse: self.reverse,
+            limit: self.limit,
+            filter: self.parse_filter(now, zone)?,
+            ..TaskQuery::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::ex_csv::Exportable;
+    use crate::shared::Task;
+    use chrono::{FixedOffset, TimeZone};
+    use dashmap::DashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn saved_view_filters_a_csv_export() {
+        let config: Config =
+            serde_json::from_str(r#"{ "views": { "ops-this-week": { "filter": "tag:ops and start<+7d", "sort": "priority" } } }"#)
+                .unwrap();
+        let now = Utc.with_ymd_and_hms(2030, 1, 1, 8, 0, 0).unwrap();
+        let zone = Zone::Fixed(FixedOffset::east_opt(0).unwrap());
+        let view = &config.views["ops-this-week"];
+        view.validate("ops-this-week", now, zone).unwrap();
+
+        let tasks = Arc::new(DashMap::new());
+        for (id, days, tag) in [(1, 1, "ops"), (2, 2, "home"), (3, 10, "ops")] {
+            let start = now + chrono::Duration::days(days);
+            let mut task = Task::new(format!("Task {}", id), String::new(), start, start + chrono::Duration::hours(1), false, None, None);
+            task.id = id;
+            task.tags = vec![tag.to_string()];
+            tasks.insert(id, task);
+        }
+        let path = std::env::temp_dir().join(format!("todo_task_view_export_{}.csv", std::process::id()));
+        let filter = view.parse_filter(now, zone).unwrap();
+        Exportable::new(tasks).export_to_csv(path.to_str().unwrap(), filter.as_ref()).await.unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let ids: Vec<String> = reader.records().map(|row| row.unwrap()[0].to_string()).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ids, ["1"]);
+    }
+
+    #[test]
+    fn reserved_and_invalid_views_are_rejected() {
+        let now = Utc.with_ymd_and_hms(2030, 1, 1, 8, 0, 0).unwrap();
+        let zone = Zone::Fixed(FixedOffset::east_opt(0).unwrap());
+        assert!(View::default().validate("list", now, zone).is_err());
+        let broken = View { filter: Some("start<".into()), ..View::default() };
+        assert!(broken.validate("broken", now, zone).is_err());
+    }
+}